@@ -1,8 +1,330 @@
 use crate::graph;
+use crate::language;
+use crate::node;
 use crate::syntaxtree;
 
-pub trait Analyzer {
-  fn extract_nodes(&mut self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph);
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::path;
+use std::sync::OnceLock;
 
-  fn extract_edges(&mut self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph);
+// Files are analyzed in parallel, so analyzers must be shareable across threads
+pub trait Analyzer: Sync {
+  // What the edge pass needs to know about a file (e.g., the call sites of each function), so that
+  // its syntax tree and source can be dropped once the node pass is done with it
+  type Summary: Clone + Send + Sync + Serialize + DeserializeOwned;
+
+  // Distinguishes the cached results of different analyzers
+  fn name(&self) -> &'static str;
+
+  // Files of other grammars are not analyzed at all
+  fn accepts(&self, _grammar: language::Grammar) -> bool {
+    true
+  }
+
+  // Called before the node pass with every file of the commit analyzed by `--rev`, for analyzers
+  // checking whether a file exists (e.g., an included header), which the working tree can't tell
+  fn set_revision_files(&self, _files: &[&path::Path]) {}
+
+  fn extract_nodes(&self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph);
+
+  fn summarize(&self, syntax_tree: &syntaxtree::SyntaxTree) -> Self::Summary;
+
+  // Called once with the summaries of all files between the two passes, for analyzers whose edges
+  // depend on more of the other files than their nodes (e.g., which Go types satisfy which
+  // interfaces). Cached edges are extracted again whenever the returned fingerprint changes.
+  // Summaries kept serialized are only deserialized as they are iterated.
+  fn link<S: Borrow<Self::Summary>>(&self, _summaries: impl Iterator<Item = S>) -> String {
+    String::new()
+  }
+
+  // Look up nodes in `graph`, which holds the nodes of all files,
+  // and add edges (and nodes unknown to `graph`) to `partial_graph`
+  fn extract_edges(
+    &self,
+    summary: &Self::Summary,
+    graph: &graph::Graph,
+    partial_graph: &mut graph::Graph,
+  );
+}
+
+// The base classes of a class as written, which is what class analyzers summarize a file as
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Inheritance {
+  pub class: String,
+  pub baseclasses: Vec<String>,
+  // How each base class is derived from, in languages telling it (e.g., C++)
+  pub attributes: Vec<graph::EdgeAttributes>,
+}
+
+// Base classes are looked up by their name, then by their unqualified name (e.g., `Base` for
+// `ns::Base` or `pkg.Base`), and shown as written if they are unknown
+pub fn extract_inheritance_edges(
+  inheritances: &[Inheritance],
+  graph: &graph::Graph,
+  partial_graph: &mut graph::Graph,
+) {
+  for inheritance in inheritances {
+    let class = match graph.get_node(&inheritance.class) {
+      Some(class) => class,
+      None => continue,
+    };
+    for (i, baseclass_name) in inheritance.baseclasses.iter().enumerate() {
+      let attributes = inheritance.attributes.get(i).cloned().unwrap_or_default();
+      let unqualified_baseclass_name = match baseclass_name.rfind([':', '.']) {
+        Some(index) => &baseclass_name[index + 1..],
+        None => baseclass_name,
+      };
+      if let Some(baseclass) = graph
+        .get_node(baseclass_name)
+        .or_else(|| graph.get_node(unqualified_baseclass_name))
+      {
+        partial_graph.add_edge_with_attributes(baseclass, class, &attributes);
+      } else {
+        let baseclass = node::Node::new_without_loc(baseclass_name);
+        partial_graph.add_node(&baseclass);
+        partial_graph.add_edge_with_attributes(&baseclass, class, &attributes);
+      }
+    }
+  }
+}
+
+// A name without the modules or packages it starts with, e.g., `Conn::new()` for
+// `net::Conn::new()` or `Foo.run()` for `com.acme.Foo.run()`, as those are named in lower case and
+// types in upper camel case
+pub fn unqualified<'a>(name: &'a str, separator: &str) -> &'a str {
+  let mut rest = name;
+  while let Some((module, next)) = rest.split_once(separator) {
+    if module.starts_with(|c: char| c.is_ascii_uppercase()) {
+      break;
+    }
+    rest = next;
+  }
+  rest
+}
+
+// The nodes of a graph by their unqualified name, or None if several of them share it
+pub fn unqualified_pool(
+  graph: &graph::Graph,
+  separator: &str,
+) -> HashMap<String, Option<node::Node>> {
+  let mut pool = HashMap::<String, Option<node::Node>>::new();
+  for (name, node) in graph.nodes.iter() {
+    pool
+      .entry(String::from(unqualified(name, separator)))
+      .and_modify(|unique| *unique = None)
+      .or_insert_with(|| Some(node.clone()));
+  }
+  pool
+}
+
+// The first candidate name found in the graph, or else the one node having its unqualified name
+pub fn lookup<'a>(
+  candidates: &[String],
+  graph: &'a graph::Graph,
+  pool: &'a HashMap<String, Option<node::Node>>,
+  separator: &str,
+) -> Option<&'a node::Node> {
+  candidates
+    .iter()
+    .find_map(|candidate| graph.get_node(candidate))
+    .or_else(|| {
+      candidates
+        .iter()
+        .find_map(|candidate| pool.get(unqualified(candidate, separator))?.as_ref())
+    })
+}
+
+#[derive(Clone, Copy)]
+pub enum Kind {
+  Call,
+  Class,
+  Include,
+}
+
+// An `Analyzer` of any summary type, which is kept serialized
+pub trait AnyAnalyzer: Sync {
+  fn set_revision_files(&self, files: &[&path::Path]);
+
+  fn extract_nodes(&self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph);
+
+  fn summarize(&self, syntax_tree: &syntaxtree::SyntaxTree) -> Vec<u8>;
+
+  fn link(&self, summaries: &[&[u8]]) -> String;
+
+  fn extract_edges(&self, summary: &[u8], graph: &graph::Graph, partial_graph: &mut graph::Graph);
+}
+
+impl<T: Analyzer> AnyAnalyzer for T {
+  fn set_revision_files(&self, files: &[&path::Path]) {
+    Analyzer::set_revision_files(self, files);
+  }
+
+  fn extract_nodes(&self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph) {
+    Analyzer::extract_nodes(self, syntax_tree, graph);
+  }
+
+  fn summarize(&self, syntax_tree: &syntaxtree::SyntaxTree) -> Vec<u8> {
+    bincode::serialize(&Analyzer::summarize(self, syntax_tree)).unwrap_or_default()
+  }
+
+  fn link(&self, summaries: &[&[u8]]) -> String {
+    Analyzer::link(
+      self,
+      summaries
+        .iter()
+        .filter_map(|summary| bincode::deserialize::<T::Summary>(summary).ok()),
+    )
+  }
+
+  fn extract_edges(&self, summary: &[u8], graph: &graph::Graph, partial_graph: &mut graph::Graph) {
+    if let Ok(summary) = bincode::deserialize(summary) {
+      Analyzer::extract_edges(self, &summary, graph, partial_graph);
+    }
+  }
+}
+
+// The analyzers of one kind provided by every language, each file being analyzed by the one of
+// its language. They share a graph, so a mixed-language project yields a single graph.
+pub struct Registry {
+  kind: Kind,
+  analyzers: Vec<Option<Box<dyn AnyAnalyzer>>>,
+  // The nodes of each language, which are all its analyzer looks up edges among. None if they are
+  // all the nodes of the graph.
+  graphs: Vec<OnceLock<Option<graph::Graph>>>,
+}
+
+// The summary of a file, along with the language whose analyzer made it
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Summary {
+  language: usize,
+  summary: Vec<u8>,
+}
+
+impl Registry {
+  pub fn new(kind: Kind) -> Self {
+    Registry {
+      kind,
+      analyzers: language::LANGUAGES
+        .iter()
+        .map(|language| language.analyzer(kind))
+        .collect(),
+      graphs: language::LANGUAGES
+        .iter()
+        .map(|_| OnceLock::new())
+        .collect(),
+    }
+  }
+
+  fn graph_of<'a>(&'a self, language: usize, graph: &'a graph::Graph) -> &'a graph::Graph {
+    let is_of = |node: &node::Node| {
+      node
+        .location
+        .iter()
+        .any(|loc| loc.language == Some(language))
+    };
+    self.graphs[language]
+      .get_or_init(|| {
+        if graph.nodes.values().all(is_of) {
+          return None;
+        }
+        let mut language_graph = graph::Graph::new();
+        language_graph.nodes = graph
+          .nodes
+          .iter()
+          .filter(|(_, node)| is_of(node))
+          .map(|(name, node)| (name.clone(), node.clone()))
+          .collect();
+        Some(language_graph)
+      })
+      .as_ref()
+      .unwrap_or(graph)
+  }
+
+  fn get(&self, grammar: language::Grammar) -> Option<(usize, &dyn AnyAnalyzer)> {
+    let language = grammar.language();
+    Some((language, self.analyzers[language].as_deref()?))
+  }
+}
+
+impl Analyzer for Registry {
+  type Summary = Summary;
+
+  fn name(&self) -> &'static str {
+    match self.kind {
+      Kind::Call => "call",
+      Kind::Class => "class",
+      Kind::Include => "include",
+    }
+  }
+
+  fn accepts(&self, grammar: language::Grammar) -> bool {
+    self.get(grammar).is_some()
+  }
+
+  fn set_revision_files(&self, files: &[&path::Path]) {
+    for analyzer in self.analyzers.iter().flatten() {
+      analyzer.set_revision_files(files);
+    }
+  }
+
+  fn extract_nodes(&self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph) {
+    if let Some((language, analyzer)) = self.get(syntax_tree.grammar) {
+      analyzer.extract_nodes(syntax_tree, graph);
+      for node in graph.nodes.values_mut() {
+        for loc in node.location.iter_mut() {
+          loc.language.get_or_insert(language);
+        }
+      }
+    }
+  }
+
+  fn summarize(&self, syntax_tree: &syntaxtree::SyntaxTree) -> Summary {
+    match self.get(syntax_tree.grammar) {
+      Some((language, analyzer)) => Summary {
+        language,
+        summary: analyzer.summarize(syntax_tree),
+      },
+      None => Summary {
+        language: syntax_tree.grammar.language(),
+        summary: vec![],
+      },
+    }
+  }
+
+  fn link<S: Borrow<Summary>>(&self, summaries: impl Iterator<Item = S>) -> String {
+    let summaries = summaries.collect::<Vec<_>>();
+    self
+      .analyzers
+      .iter()
+      .enumerate()
+      .filter_map(|(language, analyzer)| {
+        let summaries = summaries
+          .iter()
+          .map(|summary| summary.borrow())
+          .filter(|summary| summary.language == language)
+          .map(|summary| summary.summary.as_slice())
+          .collect::<Vec<_>>();
+        Some(analyzer.as_ref()?.link(&summaries))
+      })
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
+
+  fn extract_edges(
+    &self,
+    summary: &Summary,
+    graph: &graph::Graph,
+    partial_graph: &mut graph::Graph,
+  ) {
+    if let Some(Some(analyzer)) = self.analyzers.get(summary.language) {
+      analyzer.extract_edges(
+        &summary.summary,
+        self.graph_of(summary.language, graph),
+        partial_graph,
+      );
+    }
+  }
 }
@@ -0,0 +1,136 @@
+use crate::compdb;
+use crate::graph;
+use crate::syntaxtree;
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path;
+
+// Bump whenever the analyzers change what they extract, so that stale caches are discarded
+const VERSION: u32 = 8;
+
+const DIR: &str = ".ast-flow-cache";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Entry<S> {
+  pub hash: String,
+  pub errors: Vec<syntaxtree::ParseError>,
+  pub nodes: graph::Graph,
+  // What the analyzer needs to extract the edges without parsing the file again
+  pub summary: S,
+  // Edges depend on the nodes of all files, so they are tagged with the hash of those nodes
+  pub edges: Option<(String, graph::Graph)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Content<S> {
+  fingerprint: String,
+  entries: HashMap<String, Entry<S>>,
+}
+
+impl<S> Default for Content<S> {
+  fn default() -> Self {
+    Content {
+      fingerprint: String::new(),
+      entries: HashMap::new(),
+    }
+  }
+}
+
+// The nodes and edges extracted from each file. With `--cache` they are stored under
+// `.ast-flow-cache/` next to the analyzed files, so a file is only parsed again when its content
+// or compile flags change. `--watch` keeps them in memory between rounds.
+pub struct Cache<S> {
+  file: Option<path::PathBuf>,
+  content: Content<S>,
+}
+
+impl<S: Serialize + DeserializeOwned> Cache<S> {
+  pub fn new() -> Self {
+    Cache {
+      file: None,
+      content: Content::default(),
+    }
+  }
+
+  pub fn load(root: &path::Path, name: &str) -> Self {
+    let dir = if root.is_file() {
+      root.parent().unwrap_or(root)
+    } else {
+      root
+    };
+    let file = dir.join(DIR).join(format!("{}.bin", name));
+    let content = fs::read(&file)
+      .ok()
+      .and_then(|bytes| bincode::deserialize::<Content<S>>(&bytes).ok())
+      .unwrap_or_default();
+    Cache {
+      file: Some(file),
+      content,
+    }
+  }
+
+  // `settings` covers everything that affects all files (e.g., the analyzer and `--ignore-macro`).
+  // If they don't match the ones the entries were extracted with, the cache starts over.
+  pub fn validate(&mut self, settings: &[&str]) {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&VERSION.to_le_bytes());
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    for setting in settings {
+      hasher.update(setting.as_bytes());
+      hasher.update(b"\0");
+    }
+    let fingerprint = hasher.finalize().to_hex().to_string();
+    if fingerprint != self.content.fingerprint {
+      self.content = Content {
+        fingerprint,
+        entries: HashMap::new(),
+      };
+    }
+  }
+
+  pub fn get(&self, file: &str, hash: &str) -> Option<&Entry<S>> {
+    self
+      .content
+      .entries
+      .get(file)
+      .filter(|entry| entry.hash == hash)
+  }
+
+  // Replace the cached entries, dropping files that are gone
+  pub fn update(&mut self, entries: HashMap<String, Entry<S>>) -> Result<()> {
+    self.content.entries = entries;
+    let file = match self.file {
+      Some(ref file) => file,
+      None => return Ok(()),
+    };
+    if let Some(dir) = file.parent() {
+      fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create: \"{}\"!", dir.display()))?;
+    }
+    fs::write(file, bincode::serialize(&self.content)?)
+      .with_context(|| format!("Failed to write: \"{}\"!", file.display()))
+  }
+}
+
+pub fn hash_file(source: &str, command: &compdb::CompileCommand) -> String {
+  let mut hasher = blake3::Hasher::new();
+  hasher.update(source.as_bytes());
+  hasher.update(&bincode::serialize(&command.grammar).unwrap_or_default());
+  hasher.update(&bincode::serialize(&command.flags).unwrap_or_default());
+  hasher.finalize().to_hex().to_string()
+}
+
+pub fn hash_nodes(graph: &graph::Graph, fingerprint: &str) -> String {
+  let mut names = graph.nodes.keys().collect::<Vec<_>>();
+  names.sort();
+  let mut hasher = blake3::Hasher::new();
+  for name in names {
+    hasher.update(&bincode::serialize(&graph.nodes[name]).unwrap_or_default());
+  }
+  hasher.update(fingerprint.as_bytes());
+  hasher.finalize().to_hex().to_string()
+}
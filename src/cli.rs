@@ -1,4 +1,5 @@
 use clap::{Args, Parser, Subcommand};
+use serde::{Deserialize, Serialize};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -13,13 +14,44 @@ pub enum Command {
   Call(Arg),
   /// Generate class hierarchy graph
   Class(Arg),
+  /// Generate #include dependency graph
+  Include(Arg),
 }
 
-#[derive(Args)]
+#[derive(Args, Serialize, Deserialize)]
 pub struct Arg {
-  /// Specify the path to the file or directory to analyze
-  #[arg(short = 'p', long = "path", default_value("./"))]
-  pub path: std::path::PathBuf,
+  /// Use the settings of a [profile.NAME] table in .ast-flow.toml
+  #[arg(long = "profile", value_name = "NAME")]
+  pub profile: Option<String>,
+
+  /// Specify the path to the file or directory to analyze, optionally labeled (e.g., core=./libfoo) (this option can be provided multiple times)
+  #[arg(
+    short = 'p',
+    long = "path",
+    value_name = "[LABEL=]PATH",
+    default_value("./")
+  )]
+  pub paths: Vec<Root>,
+
+  /// Analyze the files built according to a compilation database (compile_commands.json)
+  #[arg(long = "compile-commands", value_name = "FILE")]
+  pub compile_commands: Option<std::path::PathBuf>,
+
+  /// Add a directory to search for included headers (this option can be provided multiple times)
+  #[arg(short = 'I', long = "include-dir", value_name = "DIR")]
+  pub include_dirs: Vec<std::path::PathBuf>,
+
+  /// Analyze files with the given extension, optionally naming the grammar that parses them (this option can be provided multiple times)
+  #[arg(long = "ext", value_name = "EXT[=GRAMMAR]")]
+  pub extensions: Vec<String>,
+
+  /// Analyze the files of a commit (e.g., a tag or a branch) instead of the working tree
+  #[arg(long = "rev", value_name = "COMMIT", conflicts_with_all = ["compile_commands", "watch"])]
+  pub rev: Option<String>,
+
+  /// Decode files that aren't UTF-8 with the given encoding (e.g., gbk or latin1) instead of guessing it
+  #[arg(long = "encoding")]
+  pub encoding: Option<String>,
 
   /// Specify a path to exclude (this option can be provided multiple times)
   #[arg(long = "exclude-path")]
@@ -41,6 +73,14 @@ pub struct Arg {
   #[arg(long = "no-default-exclude-path", default_value_t = false)]
   pub no_exclude: bool,
 
+  /// Whether to follow symbolic links when walking directories
+  #[arg(long = "follow-symlinks", default_value_t = false)]
+  pub follow_symlinks: bool,
+
+  /// Whether to analyze files ignored by .gitignore, .ignore, .git/info/exclude or .astflowignore
+  #[arg(long = "no-ignore", default_value_t = false)]
+  pub no_ignore: bool,
+
   /// Display in text mode
   #[arg(long = "text", default_value_t = true)]
   pub text: bool,
@@ -61,11 +101,93 @@ pub struct Arg {
   #[arg(long = "ignore-unknown", default_value_t = false)]
   pub ignore_unknown: bool,
 
-  /// Ignore a macro that confuses tree-sitter (this option can be provided multiple times)
+  /// Define a macro when evaluating #if conditionals (this option can be provided multiple times)
+  #[arg(short = 'D', long = "define", value_name = "MACRO[=VALUE]")]
+  pub defines: Vec<String>,
+
+  /// Undefine a macro when evaluating #if conditionals (this option can be provided multiple times)
+  #[arg(short = 'U', long = "undefine", value_name = "MACRO")]
+  pub undefines: Vec<String>,
+
+  /// Expand the macros defined in the analyzed files before parsing
+  #[arg(long = "expand-macros", default_value_t = false)]
+  pub expand_macros: bool,
+
+  /// Ignore a macro that confuses tree-sitter, given as tokens or a "re:" regex, with a "(...)" suffix to ignore its arguments too (this option can be provided multiple times)
   #[arg(long = "ignore-macro")]
   pub ignore_macros: Vec<String>,
 
+  /// Exit with an error if more than MAX_ERRORS constructs fail to parse (0 if no value is given)
+  #[arg(long = "strict", value_name = "MAX_ERRORS", num_args = 0..=1, default_missing_value = "0")]
+  pub strict: Option<usize>,
+
+  /// Number of threads used to analyze files (0 means one per CPU core)
+  #[arg(short = 'j', long = "jobs", default_value_t = 0)]
+  pub jobs: usize,
+
+  /// Whether to cache the results of each file in .ast-flow-cache/, so that unchanged files are not parsed again
+  #[arg(long = "cache", default_value_t = false)]
+  pub cache: bool,
+
+  /// Keep running and print the graph again whenever a source file changes
+  #[arg(long = "watch", default_value_t = false)]
+  pub watch: bool,
+
   /// Whether to sort child nodes by name
   #[arg(long = "sort-children", default_value_t = false)]
   pub sort_children: bool,
 }
+
+// A path to analyze, whose label (if any) is shown in the locations of its files instead of it
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Root {
+  pub label: Option<String>,
+  pub path: std::path::PathBuf,
+}
+
+impl std::str::FromStr for Root {
+  type Err = String;
+
+  // An existing path containing '=' is taken as is
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.split_once('=') {
+      Some((label, path))
+        if !label.is_empty()
+          && label
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+          && !std::path::Path::new(s).exists() =>
+      {
+        if path.is_empty() {
+          return Err(format!("No path given for label \"{}\"", label));
+        }
+        Ok(Root {
+          label: Some(String::from(label)),
+          path: std::path::PathBuf::from(path),
+        })
+      }
+      _ => Ok(Root {
+        label: None,
+        path: std::path::PathBuf::from(s),
+      }),
+    }
+  }
+}
+
+impl TryFrom<String> for Root {
+  type Error = String;
+
+  fn try_from(s: String) -> Result<Self, Self::Error> {
+    s.parse()
+  }
+}
+
+impl From<Root> for String {
+  fn from(root: Root) -> Self {
+    match root.label {
+      Some(label) => format!("{}={}", label, root.path.display()),
+      None => root.path.display().to_string(),
+    }
+  }
+}
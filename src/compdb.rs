@@ -0,0 +1,190 @@
+use crate::language;
+use crate::pathwalk;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::path;
+
+#[derive(Deserialize)]
+struct Entry {
+  directory: path::PathBuf,
+  file: path::PathBuf,
+  arguments: Option<Vec<String>>,
+  command: Option<String>,
+}
+
+// The subset of compiler flags that influences how a file should be read
+#[derive(Clone, Default, Serialize)]
+pub struct CompileFlags {
+  pub defines: Vec<(String, String)>,
+  pub undefines: Vec<String>,
+  pub include_paths: Vec<path::PathBuf>,
+}
+
+pub struct CompileCommand {
+  pub file: path::PathBuf,
+  pub grammar: language::Grammar,
+  pub flags: CompileFlags,
+}
+
+impl CompileFlags {
+  fn parse(arguments: &[String], directory: &path::Path) -> Self {
+    let mut flags = CompileFlags::default();
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+      for option in ["-D", "-U", "-I"] {
+        if let Some(value) = argument.strip_prefix(option) {
+          let value = if value.is_empty() {
+            arguments.next().map(String::as_str).unwrap_or_default()
+          } else {
+            value
+          };
+          match option {
+            "-D" => flags.define(value),
+            "-U" => flags.undefine(value),
+            _ => flags.include_paths.push(directory.join(value)),
+          }
+          break;
+        }
+      }
+    }
+    flags
+  }
+
+  // Accept `NAME` or `NAME=VALUE` as `-D` does. Later definitions override earlier ones,
+  // just as the compiler sees them.
+  pub fn define(&mut self, definition: &str) {
+    let (name, value) = definition.split_once('=').unwrap_or((definition, "1"));
+    self.defines.retain(|(defined, _)| defined != name);
+    self.undefines.retain(|undefined| undefined != name);
+    self.defines.push((String::from(name), String::from(value)));
+  }
+
+  pub fn undefine(&mut self, name: &str) {
+    self.defines.retain(|(defined, _)| defined != name);
+    self.undefines.retain(|undefined| undefined != name);
+    self.undefines.push(String::from(name));
+  }
+}
+
+// Split a shell command line into arguments, honoring quotes and backslash escapes
+fn split_command(command: &str) -> Vec<String> {
+  let mut arguments = Vec::new();
+  let mut argument = String::new();
+  let mut in_argument = false;
+  let mut quote = None;
+  let mut chars = command.chars();
+  while let Some(c) = chars.next() {
+    match (quote, c) {
+      (Some(q), c) if c == q => quote = None,
+      (Some('"'), '\\') | (None, '\\') => {
+        if let Some(c) = chars.next() {
+          argument.push(c);
+        }
+      }
+      (Some(_), c) => argument.push(c),
+      (None, '"' | '\'') => quote = Some(c),
+      (None, c) if c.is_whitespace() => {
+        if in_argument {
+          arguments.push(std::mem::take(&mut argument));
+          in_argument = false;
+        }
+        continue;
+      }
+      (None, c) => argument.push(c),
+    }
+    in_argument = true;
+  }
+  if in_argument {
+    arguments.push(argument);
+  }
+  arguments
+}
+
+// Load the translation units listed in a compilation database, together with the project headers
+// they include. Only files under `root` are kept, and they are reported relative to it.
+pub fn load(
+  database: &path::Path,
+  root: &path::Path,
+  exclude_globset: &[String],
+  extensions: &language::Extensions,
+) -> Result<Vec<CompileCommand>> {
+  let content = fs::read_to_string(database)
+    .with_context(|| format!("Failed to read: \"{}\"!", database.display()))?;
+  let entries: Vec<Entry> = serde_json::from_str(&content)
+    .with_context(|| format!("Failed to parse: \"{}\"!", database.display()))?;
+  let canonical_root = root
+    .canonicalize()
+    .with_context(|| format!("Failed to read: \"{}\"!", root.display()))?;
+  let exclude_globset = pathwalk::build_globset(exclude_globset)?;
+  let include_regex = Regex::new(r#"(?m)^\s*#\s*include\s*([<"])([^>"]+)[>"]"#).unwrap();
+
+  let mut visited = HashSet::new();
+  let mut queue = VecDeque::new();
+  for entry in entries {
+    let arguments = match (entry.arguments, entry.command) {
+      (Some(arguments), _) => arguments,
+      (None, Some(command)) => split_command(&command),
+      (None, None) => vec![],
+    };
+    let flags = CompileFlags::parse(&arguments, &entry.directory);
+    queue.push_back((entry.directory.join(entry.file), flags));
+  }
+
+  let mut commands = Vec::new();
+  while let Some((file, flags)) = queue.pop_front() {
+    let file = match file.canonicalize() {
+      Ok(file) => file,
+      Err(_) => continue,
+    };
+    if !visited.insert(file.clone()) {
+      continue;
+    }
+    let relative_path = match file.strip_prefix(&canonical_root) {
+      Ok(relative_path) => relative_path,
+      Err(_) => continue,
+    };
+    if !exclude_globset.matches(relative_path).is_empty() {
+      continue;
+    }
+
+    // Headers are not listed in the database, so follow the includes to find them
+    if let Ok(content) = fs::read(&file) {
+      let content = String::from_utf8_lossy(&content);
+      for captures in include_regex.captures_iter(&content) {
+        let header = path::Path::new(&captures[2]);
+        let quoted_dir = if &captures[1] == "\"" {
+          file.parent()
+        } else {
+          None
+        };
+        if let Some(header) = quoted_dir
+          .into_iter()
+          .chain(flags.include_paths.iter().map(path::PathBuf::as_path))
+          .map(|dir| dir.join(header))
+          .find(|header| header.is_file())
+        {
+          if extensions.grammar(&header).is_some() {
+            queue.push_back((header, flags.clone()));
+          }
+        }
+      }
+    }
+
+    // Translation units are compiled as C/C++ whatever their extension
+    let grammar = extensions.grammar(&file).unwrap_or(language::Grammar::Cpp);
+    commands.push(CompileCommand {
+      grammar,
+      file: if relative_path.as_os_str().is_empty() {
+        root.to_path_buf()
+      } else {
+        root.join(relative_path)
+      },
+      flags,
+    });
+  }
+  Ok(commands)
+}
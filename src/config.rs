@@ -0,0 +1,162 @@
+use crate::cli;
+
+use anyhow::{Context, Result};
+use clap::parser::ValueSource;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path;
+
+pub const FILE: &str = ".ast-flow.toml";
+
+// Options given as paths, which are relative to the directory of the config file
+const PATH_OPTIONS: &[&str] = &["paths", "compile_commands", "include_dirs"];
+
+// Settings read from the closest .ast-flow.toml at or above the analyzed path. Top-level keys are
+// named after the command line options (e.g., `exclude-path = ["gen/*"]`), and the keys of a
+// `[profile.NAME]` table override them when it is selected with --profile.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+  // Extension to grammar name, e.g., `cu = "cpp"`
+  pub extensions: HashMap<String, String>,
+  profile: HashMap<String, toml::Table>,
+  #[serde(flatten)]
+  options: toml::Table,
+  #[serde(skip)]
+  file: path::PathBuf,
+}
+
+impl Config {
+  pub fn load(root: &path::Path) -> Result<Self> {
+    let root = root.canonicalize().unwrap_or(root.to_path_buf());
+    let file = match root
+      .ancestors()
+      .map(|dir| dir.join(FILE))
+      .find(|file| file.is_file())
+    {
+      Some(file) => file,
+      None => return Ok(Config::default()),
+    };
+    let content = fs::read_to_string(&file)
+      .with_context(|| format!("Failed to read: \"{}\"!", file.display()))?;
+    let mut config: Config = toml::from_str(&content)
+      .with_context(|| format!("Failed to parse: \"{}\"!", file.display()))?;
+    config.file = file;
+    Ok(config)
+  }
+
+  // Fill in the options of `arg` that weren't given on the command line. `command` and `matches`
+  // are those of the subcommand `arg` was parsed from.
+  pub fn apply(
+    &self,
+    arg: cli::Arg,
+    command: &clap::Command,
+    matches: &clap::ArgMatches,
+  ) -> Result<cli::Arg> {
+    let mut options = self.options.clone();
+    if let Some(ref name) = arg.profile {
+      let profile = self
+        .profile
+        .get(name)
+        .with_context(|| format!("Unknown profile: \"{}\"!", name))?;
+      options.extend(profile.clone());
+    }
+    if options.is_empty() {
+      return Ok(arg);
+    }
+
+    let mut table = match toml::Value::try_from(&arg)? {
+      toml::Value::Table(table) => table,
+      _ => unreachable!(),
+    };
+    for (key, value) in options {
+      let option = command
+        .get_arguments()
+        .find(|option| option.get_long() == Some(&key) || option.get_id() == key.as_str())
+        .filter(|option| option.get_id() != "profile")
+        .with_context(|| {
+          format!(
+            "Unknown option in \"{}\": \"{}\"!",
+            self.file.display(),
+            key
+          )
+        })?;
+      let id = option.get_id().as_str();
+      if matches.value_source(id) == Some(ValueSource::CommandLine) {
+        continue;
+      }
+      // Options that can be provided multiple times also accept a single value
+      let value = match value {
+        toml::Value::Array(_) => value,
+        value if matches!(option.get_action(), clap::ArgAction::Append) => {
+          toml::Value::Array(vec![value])
+        }
+        value => value,
+      };
+      let value = if PATH_OPTIONS.contains(&id) {
+        self.resolve(id, value)
+      } else {
+        value
+      };
+      table.insert(String::from(id), value);
+    }
+    let arg: cli::Arg = toml::Value::Table(table)
+      .try_into()
+      .with_context(|| format!("Failed to parse: \"{}\"!", self.file.display()))?;
+    if arg.paths.is_empty() {
+      return Err(anyhow::anyhow!(
+        "No path to analyze in \"{}\"!",
+        self.file.display()
+      ));
+    }
+    Ok(arg)
+  }
+
+  fn resolve(&self, id: &str, value: toml::Value) -> toml::Value {
+    let dir = self.file.parent().unwrap_or(path::Path::new(""));
+    // Kept relative to the working directory if possible, as paths are shown as found
+    let dir = env::current_dir()
+      .and_then(|cwd| cwd.canonicalize())
+      .ok()
+      .and_then(|cwd| dir.strip_prefix(cwd).ok())
+      .map(|dir| path::Path::new(".").join(dir))
+      .unwrap_or(dir.to_path_buf());
+    match value {
+      // Labels of `--path` stay in front, e.g., `core=./libfoo`
+      toml::Value::String(path) if id == "paths" => match path.parse::<cli::Root>() {
+        Ok(root) => toml::Value::String(String::from(cli::Root {
+          path: join(&dir, &root.path),
+          ..root
+        })),
+        Err(_) => toml::Value::String(path),
+      },
+      toml::Value::String(path) => {
+        toml::Value::String(join(&dir, path::Path::new(&path)).display().to_string())
+      }
+      toml::Value::Array(paths) => toml::Value::Array(
+        paths
+          .into_iter()
+          .map(|path| self.resolve(id, path))
+          .collect(),
+      ),
+      value => value,
+    }
+  }
+}
+
+// Join a path to a directory without `.` components (e.g., `./sub` rather than `././sub`), since
+// files are shown, and cached, by the path they are found at
+fn join(dir: &path::Path, path: &path::Path) -> path::PathBuf {
+  let joined = dir
+    .join(path)
+    .components()
+    .filter(|component| *component != path::Component::CurDir)
+    .collect::<path::PathBuf>();
+  match joined.components().next() {
+    Some(path::Component::Normal(_)) => path::Path::new(".").join(joined),
+    Some(_) => joined,
+    None => path::PathBuf::from("."),
+  }
+}
@@ -3,12 +3,14 @@ use crate::graph;
 use crate::node;
 use crate::syntaxtree;
 
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::OnceLock;
 
 type Call = node::Node;
 
 pub struct CallAnalyzer {
-  qualified_function_pool: HashMap<String, Call>,
+  qualified_function_pool: OnceLock<HashMap<String, Call>>,
 }
 
 enum Context {
@@ -16,20 +18,45 @@ enum Context {
   ClassIdentifier(String),
   FunctionDefinition(usize),
   FunctionDeclarator,
-  FunctionIdentifier(Call),
+  FunctionName(String),
   CallExpression(usize),
 }
 
+// The calls made by the functions of a file, in the order they are found
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Calls {
+  // Every callee along with the function calling it, as it depends on the caller where the callee is
+  // looked up
+  callees: Vec<(String, Callee)>,
+  // From a caller to the index of its callee
+  edges: Vec<(String, usize)>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+enum Callee {
+  New,
+  Delete,
+  // Called on an object (e.g., `a.f()` or `p->f()`), so only member functions are looked up
+  Member(String),
+  Function(String),
+}
+
 impl CallAnalyzer {
   pub fn new() -> Self {
     CallAnalyzer {
-      qualified_function_pool: HashMap::new(),
+      qualified_function_pool: OnceLock::new(),
     }
   }
 }
 
 impl analyzer::Analyzer for CallAnalyzer {
-  fn extract_nodes(&mut self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph) {
+  type Summary = Calls;
+
+  fn name(&self) -> &'static str {
+    "call"
+  }
+
+  fn extract_nodes(&self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph) {
     let mut context = Vec::<Context>::new();
 
     syntax_tree.iter().for_each(|node| match context.len() {
@@ -123,28 +150,13 @@ impl analyzer::Analyzer for CallAnalyzer {
     });
   }
 
-  fn extract_edges(&mut self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph) {
-    if self.qualified_function_pool.is_empty() {
-      for (qualified_name, call) in graph.nodes.iter() {
-        if let Some(index) = qualified_name.rfind(':') {
-          let name = &qualified_name[index + 1..];
-          if !self.qualified_function_pool.contains_key(name) {
-            self
-              .qualified_function_pool
-              .insert(String::from(name), call.clone());
-          } else {
-            self
-              .qualified_function_pool
-              .get_mut(name)
-              .unwrap()
-              .merge_node(call);
-          }
-        }
-      }
-    }
-
+  fn summarize(&self, syntax_tree: &syntaxtree::SyntaxTree) -> Calls {
+    let mut calls = Calls {
+      callees: vec![],
+      edges: vec![],
+    };
     let mut context = Vec::<Context>::new();
-    let mut call_stack = Vec::<(usize, Call)>::new();
+    let mut call_stack = Vec::<(usize, usize)>::new();
 
     syntax_tree.iter().for_each(|node| match context.len() {
       0 if matches!(node.kind(), "struct_specifier" | "class_specifier") => {
@@ -185,11 +197,7 @@ impl analyzer::Analyzer for CallAnalyzer {
       ) =>
       {
         if let Context::FunctionDeclarator = context[1] {
-          if let Some(call) = graph.get_node(&format!("{}()", syntax_tree.source(&node))) {
-            context[1] = Context::FunctionIdentifier(call.clone());
-          } else {
-            context.clear();
-          }
+          context[1] = Context::FunctionName(format!("{}()", syntax_tree.source(&node)));
         }
       }
       4 if matches!(
@@ -203,13 +211,8 @@ impl analyzer::Analyzer for CallAnalyzer {
       {
         if let Context::FunctionDeclarator = context[3] {
           if let Context::ClassIdentifier(ref class) = context[1] {
-            if let Some(call) =
-              graph.get_node(&format!("{}::{}()", class, syntax_tree.source(&node)))
-            {
-              context[3] = Context::FunctionIdentifier(call.clone());
-            } else {
-              context.truncate(2);
-            }
+            context[3] =
+              Context::FunctionName(format!("{}::{}()", class, syntax_tree.source(&node)));
           } else {
             context.clear();
           }
@@ -221,7 +224,7 @@ impl analyzer::Analyzer for CallAnalyzer {
           "call_expression" | "new_expression" | "delete_expression"
         ) =>
       {
-        if let Some(Context::FunctionIdentifier(_)) = context.last() {
+        if let Some(Context::FunctionName(_)) = context.last() {
           context.push(Context::CallExpression(node.end_byte()));
         }
       }
@@ -234,7 +237,7 @@ impl analyzer::Analyzer for CallAnalyzer {
         if let Some(Context::CallExpression(pos)) = context.last() {
           let pos = *pos;
           context.pop();
-          if let Some(Context::FunctionIdentifier(ref call)) = context.last() {
+          if let Some(Context::FunctionName(ref caller)) = context.last() {
             let source = syntax_tree.source(&node);
             let call_function = if let Some(index) = source.find('<') {
               &source[..index]
@@ -256,43 +259,15 @@ impl analyzer::Analyzer for CallAnalyzer {
             if let Some(index) = function_name.find('(') {
               function_name = &function_name[..index];
             }
-            let function = &format!("{}()", function_name);
-            if node.kind() == "new" {
-              let callee = Call::new_without_loc("operator new()");
-              graph.add_node(&callee);
-              call_stack.push((pos, callee));
-            } else if node.kind() == "delete" {
-              let callee = Call::new_without_loc("operator delete()");
-              graph.add_node(&callee);
-              call_stack.push((pos, callee));
-            } else if node.kind() == "field_expression" {
-              if let Some(callee) = self.qualified_function_pool.get(function) {
-                call_stack.push((pos, callee.clone()));
-              } else {
-                let callee = Call::new_without_loc(function);
-                graph.add_node(&callee);
-                call_stack.push((pos, callee));
-              }
-            } else {
-              // If we are currently within a member function, we should first lookup in member functions and then in global functions;
-              // Otherwise, we should only look up in global functions
-              if call.name.contains("::") {
-                if let Some(callee) = self.qualified_function_pool.get(function) {
-                  call_stack.push((pos, callee.clone()));
-                  return;
-                } else if let Some(callee) = graph.get_node(function) {
-                  call_stack.push((pos, callee.clone()));
-                  return;
-                }
-              } else if let Some(callee) = graph.get_node(function) {
-                call_stack.push((pos, callee.clone()));
-                return;
-              }
-
-              let callee = Call::new_without_loc(function);
-              graph.add_node(&callee);
-              call_stack.push((pos, callee));
-            }
+            let function = format!("{}()", function_name);
+            let callee = match node.kind() {
+              "new" => Callee::New,
+              "delete" => Callee::Delete,
+              "field_expression" => Callee::Member(function),
+              _ => Callee::Function(function),
+            };
+            call_stack.push((pos, calls.callees.len()));
+            calls.callees.push((caller.clone(), callee));
           } else {
             context.pop();
             context.pop();
@@ -302,10 +277,10 @@ impl analyzer::Analyzer for CallAnalyzer {
         }
       }
       1.. => {
-        if let Some(Context::FunctionIdentifier(call)) = context.last() {
-          if let Some((pos, callee)) = call_stack.last() {
+        if let Some(Context::FunctionName(caller)) = context.last() {
+          if let Some((pos, index)) = call_stack.last() {
             if node.start_byte() + 1 >= *pos {
-              graph.add_edge(call, callee);
+              calls.edges.push((caller.clone(), *index));
               call_stack.pop();
             }
           }
@@ -329,5 +304,65 @@ impl analyzer::Analyzer for CallAnalyzer {
       }
       _ => (),
     });
+    calls
+  }
+
+  fn extract_edges(&self, calls: &Calls, graph: &graph::Graph, partial_graph: &mut graph::Graph) {
+    let qualified_function_pool = self.qualified_function_pool.get_or_init(|| {
+      let mut qualified_function_pool = HashMap::<String, Call>::new();
+      for (qualified_name, call) in graph.nodes.iter() {
+        if let Some(index) = qualified_name.rfind(':') {
+          let name = &qualified_name[index + 1..];
+          if !qualified_function_pool.contains_key(name) {
+            qualified_function_pool.insert(String::from(name), call.clone());
+          } else {
+            qualified_function_pool
+              .get_mut(name)
+              .unwrap()
+              .merge_node(call);
+          }
+        }
+      }
+      qualified_function_pool
+    });
+
+    let mut unknown = |function: &str| {
+      let callee = Call::new_without_loc(function);
+      partial_graph.add_node(&callee);
+      callee
+    };
+    // Functions unknown to `graph` have no node to start an edge from, so their calls are skipped
+    let callees = calls
+      .callees
+      .iter()
+      .map(|(caller, callee)| {
+        let call = graph.get_node(caller)?;
+        Some(match callee {
+          Callee::New => unknown("operator new()"),
+          Callee::Delete => unknown("operator delete()"),
+          Callee::Member(function) => match qualified_function_pool.get(function) {
+            Some(callee) => callee.clone(),
+            None => unknown(function),
+          },
+          // If we are currently within a member function, we should first lookup in member functions and then in global functions;
+          // Otherwise, we should only look up in global functions
+          Callee::Function(function) => match (
+            call.name.contains("::"),
+            qualified_function_pool.get(function),
+            graph.get_node(function),
+          ) {
+            (true, Some(callee), _) => callee.clone(),
+            (_, _, Some(callee)) => callee.clone(),
+            _ => unknown(function),
+          },
+        })
+      })
+      .collect::<Vec<_>>();
+
+    for (caller, index) in calls.edges.iter() {
+      if let (Some(call), Some(callee)) = (graph.get_node(caller), &callees[*index]) {
+        partial_graph.add_edge(call, callee);
+      }
+    }
   }
 }
@@ -3,146 +3,334 @@ use crate::graph;
 use crate::node;
 use crate::syntaxtree;
 
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
 type Class = node::Node;
 
-pub struct ClassAnalyzer;
+pub struct ClassAnalyzer {
+  class_pool: OnceLock<HashMap<String, Option<String>>>,
+}
 
 enum Context {
   ClassSpecifier(usize),
   ClassIdentifier(Class),
+  ClassName(String),
   BaseClassClause(usize),
   AliasDeclaration,
   TypeDefinition(usize),
 }
 
+// The namespaces and classes around a node, each along with the bytes where it starts and ends
+// and where its name starts. Inline and anonymous namespaces are left out of qualified names, since
+// their members are found without them.
+struct Scopes {
+  scopes: Vec<(usize, usize, usize, Option<String>)>,
+}
+
 impl ClassAnalyzer {
   pub fn new() -> Self {
-    ClassAnalyzer {}
+    ClassAnalyzer {
+      class_pool: OnceLock::new(),
+    }
   }
 }
 
+impl Scopes {
+  fn new() -> Self {
+    Scopes { scopes: vec![] }
+  }
+
+  // Leave the scopes ending before `node`, and enter the one it opens, if any
+  fn visit(&mut self, syntax_tree: &syntaxtree::SyntaxTree, node: &tree_sitter::Node) {
+    while self
+      .scopes
+      .last()
+      .is_some_and(|(_, end, _, _)| node.start_byte() >= *end)
+    {
+      self.scopes.pop();
+    }
+    let (name, scope) = match node.kind() {
+      "namespace_definition" => {
+        let name = node.child_by_field_name("name");
+        let scope = name
+          .filter(|_| node.child(0).is_none_or(|token| token.kind() != "inline"))
+          .map(|name| self.qualify(syntax_tree.source(&name), node.start_byte()));
+        (name, scope)
+      }
+      "struct_specifier" | "class_specifier" if node.child_by_field_name("body").is_some() => {
+        let name = node.child_by_field_name("name");
+        let scope = name
+          .map(|name| self.qualify(&template_name(syntax_tree.source(&name)), node.start_byte()));
+        (name, scope)
+      }
+      _ => return,
+    };
+    let name = name.map_or(node.start_byte(), |name| name.start_byte());
+    self
+      .scopes
+      .push((node.start_byte(), node.end_byte(), name, scope));
+  }
+
+  // Qualify a name by the scopes started before `start`, e.g., `net::Socket` for `Socket`. A
+  // scope doesn't qualify its own name, which may be taken for an alias in code failing to parse.
+  fn qualify(&self, name: &str, start: usize) -> String {
+    match self
+      .scopes
+      .iter()
+      .rev()
+      .filter(|(scope_start, _, name, _)| *scope_start < start && *name != start)
+      .find_map(|(_, _, _, scope)| scope.as_ref())
+    {
+      Some(scope) => format!("{}::{}", scope, name),
+      None => String::from(name),
+    }
+  }
+}
+
+// Look up a name from a scope, then from the scopes enclosing it
+fn lookup<'a>(graph: &'a graph::Graph, scope: Option<&str>, name: &str) -> Option<&'a Class> {
+  let mut scope = scope;
+  while let Some(prefix) = scope {
+    if let Some(class) = graph.get_node(&format!("{}::{}", prefix, name)) {
+      return Some(class);
+    }
+    scope = prefix.rsplit_once("::").map(|(prefix, _)| prefix);
+  }
+  graph.get_node(name)
+}
+
+// A name without its template arguments, e.g., `Base` for `Base<T>`
+fn template_name(name: &str) -> String {
+  match name.find('<') {
+    Some(index) => String::from(&name[..index]),
+    None => String::from(name),
+  }
+}
+
+// The name of a class as written where it is defined, e.g., `net::Socket` for `class net::Socket`
+fn written_name<'a>(syntax_tree: &'a syntaxtree::SyntaxTree, node: &tree_sitter::Node) -> &'a str {
+  let mut name = *node;
+  while let Some(parent) = name
+    .parent()
+    .filter(|parent| parent.kind() == "qualified_identifier")
+  {
+    name = parent;
+  }
+  syntax_tree.source(&name)
+}
+
 impl analyzer::Analyzer for ClassAnalyzer {
-  fn extract_nodes(&mut self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph) {
+  type Summary = Vec<analyzer::Inheritance>;
+
+  fn name(&self) -> &'static str {
+    "class"
+  }
+
+  fn extract_nodes(&self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph) {
     let mut context = Vec::<Context>::new();
+    let mut scopes = Scopes::new();
+    let mut class_start = 0;
 
-    syntax_tree.iter().for_each(|node| match context.len() {
-      0 if matches!(node.kind(), "struct_specifier" | "class_specifier") => {
-        let source = syntax_tree.source(&node);
-        if source.find('{').is_some() && source.ends_with('}') {
-          context.push(Context::ClassSpecifier(node.end_byte()));
+    syntax_tree.iter().for_each(|node| {
+      scopes.visit(syntax_tree, &node);
+      match context.len() {
+        0 if matches!(node.kind(), "struct_specifier" | "class_specifier") => {
+          let source = syntax_tree.source(&node);
+          if source.find('{').is_some() && source.ends_with('}') {
+            context.push(Context::ClassSpecifier(node.end_byte()));
+            class_start = node.start_byte();
+          }
         }
-      }
-      0 if matches!(node.kind(), "alias_declaration") => context.push(Context::AliasDeclaration),
-      0 if matches!(node.kind(), "type_definition") => context.push(Context::TypeDefinition(0)),
-      1 if matches!(node.kind(), "template_type")
-        && matches!(context[0], Context::TypeDefinition(0)) =>
-      {
-        context[0] = Context::TypeDefinition(node.end_byte());
-      }
-      1 if matches!(node.kind(), "type_identifier") => match context[0] {
-        Context::TypeDefinition(0) => context[0] = Context::TypeDefinition(node.end_byte()),
-        Context::ClassSpecifier(_) | Context::ClassIdentifier(_) => {
-          context[0] = Context::ClassIdentifier(Class::new(
-            syntax_tree.source(&node),
-            &syntax_tree.file,
-            node.start_position().row + 1,
-          ));
+        0 if matches!(node.kind(), "alias_declaration") => context.push(Context::AliasDeclaration),
+        0 if matches!(node.kind(), "type_definition") => context.push(Context::TypeDefinition(0)),
+        1 if matches!(node.kind(), "template_type")
+          && matches!(context[0], Context::TypeDefinition(0)) =>
+        {
+          context[0] = Context::TypeDefinition(node.end_byte());
         }
-        _ => {
-          if let Context::TypeDefinition(end_byte) = context[0] {
-            if node.end_byte() <= end_byte {
-              return;
-            }
+        1 if matches!(node.kind(), "type_identifier") => match context[0] {
+          Context::TypeDefinition(0) => context[0] = Context::TypeDefinition(node.end_byte()),
+          Context::ClassSpecifier(_) | Context::ClassIdentifier(_) => {
+            context[0] = Context::ClassIdentifier(Class::new(
+              &scopes.qualify(written_name(syntax_tree, &node), class_start),
+              &syntax_tree.file,
+              node.start_position().row + 1,
+            ));
           }
-          context.pop();
-          let class = Class::new_alias(
-            syntax_tree.source(&node),
-            &syntax_tree.file,
-            node.start_position().row + 1,
-          );
-          graph.add_node(&class);
-        }
-      },
-      1.. => {
-        if matches!(node.kind(), "field_declaration_list" | "base_class_clause") {
-          if context.len() == 1 {
-            if let Context::ClassIdentifier(ref class) = context[0] {
-              graph.add_node(class);
+          _ => {
+            if let Context::TypeDefinition(end_byte) = context[0] {
+              if node.end_byte() <= end_byte {
+                return;
+              }
             }
+            context.pop();
+            let class = Class::new_alias(
+              &scopes.qualify(syntax_tree.source(&node), node.start_byte()),
+              &syntax_tree.file,
+              node.start_position().row + 1,
+            );
+            graph.add_node(&class);
           }
-          context.clear();
-        } else if let Context::ClassSpecifier(pos) = context[0] {
-          if node.start_byte() + 1 >= pos {
+        },
+        1.. => {
+          if matches!(node.kind(), "field_declaration_list" | "base_class_clause") {
+            if context.len() == 1 {
+              if let Context::ClassIdentifier(ref class) = context[0] {
+                graph.add_node(class);
+              }
+            }
             context.clear();
+          } else if let Context::ClassSpecifier(pos) = context[0] {
+            if node.start_byte() + 1 >= pos {
+              context.clear();
+            }
           }
         }
+        _ => (),
       }
-      _ => (),
     });
   }
 
-  fn extract_edges(&mut self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph) {
+  fn summarize(&self, syntax_tree: &syntaxtree::SyntaxTree) -> Vec<analyzer::Inheritance> {
+    let mut inheritances = Vec::<analyzer::Inheritance>::new();
     let mut context = Vec::<Context>::new();
+    let mut scopes = Scopes::new();
+    let mut class_start = 0;
+    // Classes derive privately by default, and structs publicly
+    let mut default_access = graph::Access::Private;
+    let mut attributes = graph::EdgeAttributes::default();
 
-    syntax_tree.iter().for_each(|node| match context.len() {
-      0 if matches!(node.kind(), "struct_specifier" | "class_specifier") => {
-        let source = syntax_tree.source(&node);
-        if source.find('{').is_some() && source.ends_with('}') {
-          context.push(Context::ClassSpecifier(node.end_byte()));
+    syntax_tree.iter().for_each(|node| {
+      scopes.visit(syntax_tree, &node);
+      match context.len() {
+        0 if matches!(node.kind(), "struct_specifier" | "class_specifier") => {
+          let source = syntax_tree.source(&node);
+          if source.find('{').is_some() && source.ends_with('}') {
+            context.push(Context::ClassSpecifier(node.end_byte()));
+            class_start = node.start_byte();
+            default_access = match node.kind() {
+              "struct_specifier" => graph::Access::Public,
+              _ => graph::Access::Private,
+            };
+          }
         }
-      }
-      1 if matches!(node.kind(), "type_identifier") => {
-        if let Some(class) = graph.get_node(syntax_tree.source(&node)) {
-          context.push(Context::ClassIdentifier(class.clone()));
-        } else {
-          context.pop();
+        1 if matches!(node.kind(), "type_identifier") => {
+          context.push(Context::ClassName(
+            scopes.qualify(written_name(syntax_tree, &node), class_start),
+          ));
         }
-      }
-      2 if matches!(node.kind(), "base_class_clause") => context.push(Context::BaseClassClause(0)),
-      3 if matches!(
-        node.kind(),
-        "type_identifier" | "template_type" | "qualified_identifier"
-      ) =>
-      {
-        if let Context::ClassIdentifier(ref class) = context[1] {
+        2 if matches!(node.kind(), "base_class_clause") => {
+          if let Context::ClassName(ref class) = context[1] {
+            inheritances.push(analyzer::Inheritance {
+              class: class.clone(),
+              baseclasses: vec![],
+              attributes: vec![],
+            });
+          }
+          context.push(Context::BaseClassClause(0));
+          attributes = graph::EdgeAttributes::default();
+        }
+        // e.g., `public virtual` in `: public virtual Base`
+        3 if matches!(node.kind(), "access_specifier" | "virtual") => {
+          if let Context::BaseClassClause(pos) = context[2] {
+            if node.start_byte() > pos {
+              match syntax_tree.source(&node).trim() {
+                "public" => attributes.access = Some(graph::Access::Public),
+                "protected" => attributes.access = Some(graph::Access::Protected),
+                "private" => attributes.access = Some(graph::Access::Private),
+                _ => attributes.is_virtual = true,
+              }
+            }
+          }
+        }
+        3 if matches!(
+          node.kind(),
+          "type_identifier" | "template_type" | "qualified_identifier"
+        ) =>
+        {
           if let Context::BaseClassClause(pos) = context[2] {
             if node.start_byte() > pos {
-              let source = syntax_tree.source(&node);
-              let baseclass_name = if let Some(index) = source.find('<') {
-                &source[..index]
-              } else {
-                source
-              };
-              let unqualified_baseclass_name = if let Some(index) = baseclass_name.rfind(':') {
-                &baseclass_name[index + 1..]
-              } else {
-                baseclass_name
-              };
-              if let Some(baseclass) = graph.get_node(unqualified_baseclass_name) {
-                let baseclass = baseclass.clone();
-                graph.add_edge(&baseclass, class);
-              } else {
-                let baseclass = Class::new_without_loc(baseclass_name);
-                graph.add_node(&baseclass);
-                graph.add_edge(&baseclass, class);
+              if let Some(inheritance) = inheritances.last_mut() {
+                inheritance
+                  .baseclasses
+                  .push(template_name(syntax_tree.source(&node)));
+                let mut attributes = std::mem::take(&mut attributes);
+                attributes.access.get_or_insert(default_access);
+                inheritance.attributes.push(attributes);
               }
               context[2] = Context::BaseClassClause(node.end_byte());
             }
           }
-        } else {
-          context.clear();
         }
-      }
-      1.. => {
-        if node.kind() == "field_declaration_list" {
-          context.clear();
-        } else if let Context::ClassSpecifier(pos) = context[0] {
-          if node.start_byte() + 1 >= pos {
+        1.. => {
+          if node.kind() == "field_declaration_list" {
             context.clear();
+          } else if let Context::ClassSpecifier(pos) = context[0] {
+            if node.start_byte() + 1 >= pos {
+              context.clear();
+            }
           }
         }
+        _ => (),
+      }
+    });
+    inheritances
+  }
+
+  // Base classes are looked up as C++ does, from the scope of the derived class outwards (e.g.,
+  // `Base` in `a::b::Derived` is `a::b::Base`, `a::Base` or `Base`). Since `using` directives are
+  // not followed, they are then looked up by their unqualified name if only one class has it, and
+  // shown as written otherwise.
+  fn extract_edges(
+    &self,
+    inheritances: &Vec<analyzer::Inheritance>,
+    graph: &graph::Graph,
+    partial_graph: &mut graph::Graph,
+  ) {
+    let class_pool = self.class_pool.get_or_init(|| {
+      let mut class_pool = HashMap::<String, Option<String>>::new();
+      for qualified_name in graph.nodes.keys() {
+        let name = match qualified_name.rfind("::") {
+          Some(index) => &qualified_name[index + 2..],
+          None => qualified_name,
+        };
+        class_pool
+          .entry(String::from(name))
+          .and_modify(|unique| *unique = None)
+          .or_insert_with(|| Some(qualified_name.clone()));
       }
-      _ => (),
+      class_pool
     });
+
+    for inheritance in inheritances {
+      let class = match graph.get_node(&inheritance.class) {
+        Some(class) => class,
+        None => continue,
+      };
+      let scope = inheritance.class.rsplit_once("::").map(|(scope, _)| scope);
+      for (i, baseclass_name) in inheritance.baseclasses.iter().enumerate() {
+        let attributes = inheritance.attributes.get(i).cloned().unwrap_or_default();
+        let baseclass = match baseclass_name.strip_prefix("::") {
+          Some(baseclass_name) => graph.get_node(baseclass_name),
+          None => lookup(graph, scope, baseclass_name),
+        };
+        let unqualified_baseclass_name = match baseclass_name.rfind("::") {
+          Some(index) => &baseclass_name[index + 2..],
+          None => baseclass_name.trim_start_matches(':'),
+        };
+        match baseclass.or_else(|| {
+          let qualified_name = class_pool.get(unqualified_baseclass_name)?.as_ref()?;
+          graph.get_node(qualified_name)
+        }) {
+          Some(baseclass) => partial_graph.add_edge_with_attributes(baseclass, class, &attributes),
+          None => {
+            let baseclass = Class::new_without_loc(baseclass_name);
+            partial_graph.add_node(&baseclass);
+            partial_graph.add_edge_with_attributes(&baseclass, class, &attributes);
+          }
+        }
+      }
+    }
   }
 }
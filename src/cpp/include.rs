@@ -0,0 +1,183 @@
+use crate::analyzer;
+use crate::graph;
+use crate::node;
+use crate::syntaxtree;
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path;
+use std::sync::OnceLock;
+
+type Header = node::Node;
+
+// The includes of a file, along with where they are searched
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Includes {
+  file: String,
+  include_paths: Vec<path::PathBuf>,
+  includes: Vec<Include>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Include {
+  spelling: String,
+  name: String,
+  quoted: bool,
+}
+
+pub struct IncludeAnalyzer {
+  file_pool: OnceLock<HashMap<path::PathBuf, Header>>,
+  // The files of the commit analyzed by `--rev`, by their normalized path
+  revision_files: OnceLock<HashSet<path::PathBuf>>,
+}
+
+impl IncludeAnalyzer {
+  pub fn new() -> Self {
+    IncludeAnalyzer {
+      file_pool: OnceLock::new(),
+      revision_files: OnceLock::new(),
+    }
+  }
+
+  // Files are named as they were found (e.g., `./src/a.h` or `/abs/include/a.h`), so compare them
+  // by their canonical path, or a lexically normalized one if they don't exist in the working tree.
+  // Those of a commit are only compared lexically, since the working tree may differ.
+  fn file_key(&self, file: &path::Path) -> path::PathBuf {
+    match self.revision_files.get() {
+      Some(_) => revision_key(file),
+      None => file.canonicalize().unwrap_or_else(|_| normalize(file)),
+    }
+  }
+
+  fn exists(&self, file: &path::Path) -> bool {
+    match self.revision_files.get() {
+      Some(files) => files.contains(&revision_key(file)),
+      None => file.is_file(),
+    }
+  }
+}
+
+// Files of a commit are named relative to the repository, e.g., `src/a.h` for `./src/a.h`
+fn revision_key(file: &path::Path) -> path::PathBuf {
+  let file = normalize(file);
+  match file.strip_prefix(".") {
+    Ok(file) => file.to_path_buf(),
+    Err(_) => file,
+  }
+}
+
+fn normalize(file: &path::Path) -> path::PathBuf {
+  let mut normalized = path::PathBuf::new();
+  for component in file.components() {
+    match component {
+      path::Component::CurDir if normalized.as_os_str().is_empty() => normalized.push("."),
+      path::Component::CurDir => (),
+      path::Component::ParentDir
+        if matches!(
+          normalized.components().next_back(),
+          Some(path::Component::Normal(_))
+        ) =>
+      {
+        normalized.pop();
+      }
+      component => normalized.push(component),
+    }
+  }
+  normalized
+}
+
+impl analyzer::Analyzer for IncludeAnalyzer {
+  type Summary = Includes;
+
+  fn name(&self) -> &'static str {
+    "include"
+  }
+
+  fn set_revision_files(&self, files: &[&path::Path]) {
+    let _ = self
+      .revision_files
+      .set(files.iter().map(|file| revision_key(file)).collect());
+  }
+
+  fn extract_nodes(&self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph) {
+    graph.add_node(&Header::new(&syntax_tree.file, &syntax_tree.file, 1));
+  }
+
+  fn summarize(&self, syntax_tree: &syntaxtree::SyntaxTree) -> Includes {
+    let mut includes = vec![];
+    for node in syntax_tree.iter() {
+      if node.kind() != "preproc_include" {
+        continue;
+      }
+      let path = match node.child_by_field_name("path") {
+        Some(path) => path,
+        None => continue,
+      };
+      let spelling = syntax_tree.source(&path).trim();
+      let (quoted, name) = match path.kind() {
+        "string_literal" => (true, spelling.trim_matches('"')),
+        "system_lib_string" => (
+          false,
+          spelling.trim_start_matches('<').trim_end_matches('>'),
+        ),
+        // e.g., `#include HEADER`, which only the compiler can resolve
+        _ => continue,
+      };
+      includes.push(Include {
+        spelling: String::from(spelling),
+        name: String::from(name),
+        quoted,
+      });
+    }
+    Includes {
+      file: syntax_tree.file.clone(),
+      include_paths: syntax_tree.include_paths.clone(),
+      includes,
+    }
+  }
+
+  fn extract_edges(
+    &self,
+    includes: &Includes,
+    graph: &graph::Graph,
+    partial_graph: &mut graph::Graph,
+  ) {
+    let file_pool = self.file_pool.get_or_init(|| {
+      graph
+        .nodes
+        .values()
+        .map(|header| (self.file_key(path::Path::new(&header.name)), header.clone()))
+        .collect()
+    });
+    let includer = match graph.get_node(&includes.file) {
+      Some(includer) => includer,
+      None => return,
+    };
+    let dir = path::Path::new(&includes.file)
+      .parent()
+      .unwrap_or(path::Path::new(""));
+
+    for include in includes.includes.iter() {
+      // Quoted includes are searched next to the includer first, angled ones only in -I paths
+      let header = include
+        .quoted
+        .then_some(dir)
+        .into_iter()
+        .chain(includes.include_paths.iter().map(path::PathBuf::as_path))
+        .map(|dir| dir.join(&include.name))
+        .find_map(|file| match file_pool.get(&self.file_key(&file)) {
+          Some(header) => Some(header.clone()),
+          // Found, but not analyzed (e.g., excluded), so it is shown as unknown
+          None if self.exists(&file) => Some(Header::new_without_loc(
+            &normalize(&file).display().to_string(),
+          )),
+          None => None,
+        })
+        .unwrap_or_else(|| Header::new_without_loc(&include.spelling));
+      if header.location.is_empty() {
+        partial_graph.add_node(&header);
+      }
+      partial_graph.add_edge(includer, &header);
+    }
+  }
+}
@@ -1,2 +1,59 @@
 pub mod call;
 pub mod class;
+pub mod include;
+
+use crate::analyzer;
+use crate::language::{self, Grammar};
+
+// C and C++, which share their analyzers
+pub struct Cpp;
+
+impl language::Language for Cpp {
+  fn grammars(&self) -> &'static [(&'static str, Grammar)] {
+    &[
+      ("c", Grammar::C),
+      ("cpp", Grammar::Cpp),
+      ("c++", Grammar::Cpp),
+      ("cxx", Grammar::Cpp),
+    ]
+  }
+
+  // `.h` headers are shared by C and C++, see `language::Extensions::c_headers`
+  fn extensions(&self) -> &'static [(&'static str, Grammar)] {
+    &[
+      ("c", Grammar::C),
+      ("cc", Grammar::Cpp),
+      ("cpp", Grammar::Cpp),
+      ("cxx", Grammar::Cpp),
+      ("c++", Grammar::Cpp),
+      ("h", Grammar::Cpp),
+      ("hh", Grammar::Cpp),
+      ("hpp", Grammar::Cpp),
+      ("hxx", Grammar::Cpp),
+      ("h++", Grammar::Cpp),
+      ("inl", Grammar::Cpp),
+      ("ipp", Grammar::Cpp),
+      ("tpp", Grammar::Cpp),
+      ("ixx", Grammar::Cpp),
+    ]
+  }
+
+  fn tree_sitter(&self, grammar: Grammar) -> tree_sitter::Language {
+    match grammar {
+      Grammar::C => tree_sitter_c::language(),
+      _ => tree_sitter_cpp::language(),
+    }
+  }
+
+  fn preprocessed(&self) -> bool {
+    true
+  }
+
+  fn analyzer(&self, kind: analyzer::Kind) -> Option<Box<dyn analyzer::AnyAnalyzer>> {
+    Some(match kind {
+      analyzer::Kind::Call => Box::new(call::CallAnalyzer::new()),
+      analyzer::Kind::Class => Box::new(class::ClassAnalyzer::new()),
+      analyzer::Kind::Include => Box::new(include::IncludeAnalyzer::new()),
+    })
+  }
+}
@@ -129,34 +129,41 @@ impl<'a> Display<'a> {
     let nodes = self.filter_root_nodes(self.graph.nodes.values().collect::<Vec<_>>());
     let nodes = self.filter_nodes(nodes);
 
+    // Nodes are grouped by file, or by package in languages having them. Those of a labeled root
+    // are grouped together after the others.
+    let group = |loc: &'a node::Location| (&loc.label, loc.package.as_ref().unwrap_or(&loc.file));
     let mut files = HashSet::new();
     for node in nodes.iter() {
       node.location.iter().for_each(|loc| {
-        files.insert(&loc.file);
+        files.insert(group(loc));
       });
     }
     let mut files = Vec::from_iter(files);
-    files.sort_by_key(|file| file.to_lowercase());
+    files.sort_by_key(|(label, file)| (*label, file.to_lowercase()));
 
     let mut text = String::new();
-    for file in files {
+    for (label, file) in files {
       let mut nodes_in_file = nodes
         .iter()
-        .filter(|node| node.location.iter().any(|loc| loc.file == *file))
+        .filter(|node| node.location.iter().any(|loc| group(loc) == (label, file)))
         .collect::<Vec<_>>();
       if !nodes_in_file.is_empty() {
         nodes_in_file.sort_by_key(|node| node.name.to_lowercase());
+        let file = match label {
+          Some(label) => format!("{}:{}", label, file),
+          None => String::from(file),
+        };
         text.push_str(&format!(
           "{}\n",
           if self.color {
-            color::color(file, Color::LightMagenta)
+            color::color(&file, Color::LightMagenta)
           } else {
-            String::from(file)
+            file
           }
         ));
         let num = nodes_in_file.len();
         for (i, u) in nodes_in_file.into_iter().enumerate() {
-          text.push_str(&self.node_to_text(u, vec![i + 1 == num], &mut HashSet::new()));
+          text.push_str(&self.node_to_text(u, None, vec![i + 1 == num], &mut HashSet::new()));
         }
         text.push('\n');
       }
@@ -178,7 +185,7 @@ impl<'a> Display<'a> {
         ));
         let num = nodes_in_unknown.len();
         for (i, u) in nodes_in_unknown.into_iter().enumerate() {
-          text.push_str(&self.node_to_text(u, vec![i + 1 == num], &mut HashSet::new()));
+          text.push_str(&self.node_to_text(u, None, vec![i + 1 == num], &mut HashSet::new()));
         }
       }
     }
@@ -189,7 +196,14 @@ impl<'a> Display<'a> {
     text
   }
 
-  fn node_to_text(&self, u: &node::Node, end: Vec<bool>, visited: &mut HashSet<String>) -> String {
+  // A node is followed by the attributes of the edge leading to it, if any (e.g., `(private)`)
+  fn node_to_text(
+    &self,
+    u: &node::Node,
+    attributes: Option<&graph::EdgeAttributes>,
+    end: Vec<bool>,
+    visited: &mut HashSet<String>,
+  ) -> String {
     let depth = end.len() - 1;
     if self.max_depth != -1 && depth as i32 > self.max_depth {
       String::default()
@@ -227,7 +241,10 @@ impl<'a> Display<'a> {
       };
 
       const DISPLAY_WIDTH: usize = 100;
-      let lines = u.to_string();
+      let lines = match attributes {
+        Some(attributes) => format!("{} ({})", u, attributes),
+        None => u.to_string(),
+      };
       let lines = lines.split('\n');
       let mut first_line = true;
       for line in lines {
@@ -280,7 +297,12 @@ impl<'a> Display<'a> {
           for (i, v) in nodes.into_iter().enumerate() {
             let mut end = end.clone();
             end.push(i + 1 == num);
-            text.push_str(&self.node_to_text(&v, end, &mut visited.clone()));
+            text.push_str(&self.node_to_text(
+              &v,
+              self.graph.get_attributes(u, &v),
+              end,
+              &mut visited.clone(),
+            ));
           }
         }
       }
@@ -295,8 +317,27 @@ impl<'a> Display<'a> {
     let mut text = String::from(
       "digraph g {\nnode [margin=0,width=.5,height=.2];edge [arrowsize=.5,arrowhead=vee];\n",
     );
+    // Nodes defined under a labeled root are drawn in a cluster named after it
+    let mut clusters = Vec::<(&String, Vec<&node::Node>)>::new();
     for node in nodes.iter() {
-      text.push_str(&format!("\"{}\";", node.name));
+      match node.location.iter().find_map(|loc| loc.label.as_ref()) {
+        Some(label) => match clusters.iter_mut().find(|(name, _)| *name == label) {
+          Some((_, nodes)) => nodes.push(node),
+          None => clusters.push((label, vec![node])),
+        },
+        None => text.push_str(&format!("\"{}\";", node.name)),
+      }
+    }
+    clusters.sort_by_key(|(label, _)| *label);
+    for (label, nodes) in clusters {
+      text.push_str(&format!(
+        "subgraph \"cluster_{}\" {{label=\"{}\";",
+        label, label
+      ));
+      for node in nodes {
+        text.push_str(&format!("\"{}\";", node.name));
+      }
+      text += "}\n";
     }
     for node in nodes {
       text.push_str(&self.node_to_dot(node));
@@ -311,7 +352,11 @@ impl<'a> Display<'a> {
       if let Some(nodes) = self.graph.get_adjacencies(u) {
         for v in nodes {
           if !self.ignore_unknown || !v.location.is_empty() {
-            text.push_str(&format!("\"{}\"->\"{}\";", v.name, u.name));
+            text.push_str(&format!("\"{}\"->\"{}\"", v.name, u.name));
+            if let Some(style) = self.graph.get_attributes(u, v).and_then(edge_style) {
+              text.push_str(&format!("[style=\"{}\"]", style));
+            }
+            text.push(';');
           }
         }
       }
@@ -319,3 +364,15 @@ impl<'a> Display<'a> {
     text
   }
 }
+
+// Private and protected inheritances are drawn dashed and dotted, and virtual ones bold
+fn edge_style(attributes: &graph::EdgeAttributes) -> Option<String> {
+  let access = match attributes.access {
+    Some(graph::Access::Private) => Some("dashed"),
+    Some(graph::Access::Protected) => Some("dotted"),
+    _ => None,
+  };
+  let is_virtual = attributes.is_virtual.then_some("bold");
+  let style = access.into_iter().chain(is_virtual).collect::<Vec<_>>();
+  (!style.is_empty()).then(|| style.join(","))
+}
@@ -1,54 +1,487 @@
 use crate::analyzer;
+use crate::cache;
 use crate::cli;
+use crate::compdb;
+use crate::config;
 use crate::display;
+use crate::git;
 use crate::graph;
+use crate::language;
 use crate::pathwalk;
+use crate::preprocessor;
 use crate::syntaxtree;
 
+use anyhow::{Context, Result};
+use notify::Watcher;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path;
+use std::sync::mpsc;
+use std::thread;
+use std::time;
+
 pub struct Driver;
 
+// Where the sources are read from, and how they are decoded
+struct Reader<'a> {
+  revision: Option<&'a git::Revision>,
+  encoding: Option<&'static encoding_rs::Encoding>,
+}
+
+// A file along with what has been extracted from it. Its syntax tree is dropped after the node
+// pass, since the summary holds what the edge pass needs.
+struct Unit<S> {
+  command: compdb::CompileCommand,
+  entry: cache::Entry<S>,
+}
+
 impl Driver {
-  pub fn run<T: analyzer::Analyzer>(analyzer: &mut T, arg: &cli::Arg) -> String {
-    let files = pathwalk::FileIterator::new(&arg.path, &arg.exclude_globset, arg.no_exclude);
-    match files {
-      Err(e) => {
+  pub fn run<T: analyzer::Analyzer>(analyzer: &T, arg: &cli::Arg) -> Result<String> {
+    let mut cache = arg
+      .cache
+      .then(|| cache::Cache::load(&arg.paths[0].path, analyzer.name()));
+    Driver::render(analyzer, arg, cache.as_mut())
+  }
+
+  // Print the graph again whenever a source file under a `--path` changes. Between rounds the
+  // extraction results are kept in memory, so only the changed files are parsed again.
+  pub fn watch<T: analyzer::Analyzer>(new_analyzer: impl Fn() -> T, arg: &cli::Arg) -> Result<()> {
+    let mut cache = if arg.cache {
+      cache::Cache::load(&arg.paths[0].path, new_analyzer().name())
+    } else {
+      cache::Cache::new()
+    };
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    for root in arg.paths.iter() {
+      watcher
+        .watch(&root.path, notify::RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch: \"{}\"!", root.path.display()))?;
+    }
+
+    loop {
+      // Analyzers may hold lookup tables built for a single graph, so each round gets a new one
+      let output = Driver::render(&new_analyzer(), arg, Some(&mut cache)).unwrap_or_else(|e| {
         eprintln!("[Error] {}", e);
         String::default()
+      });
+      let mut stdout = io::stdout();
+      if stdout.is_terminal() {
+        write!(stdout, "\x1b[2J\x1b[H")?;
+      }
+      writeln!(stdout, "{}", output)?;
+      stdout.flush()?;
+
+      let extensions = Driver::extensions(arg).ok();
+      loop {
+        match receiver.recv()? {
+          Err(e) => eprintln!("[Warning] {}", e),
+          Ok(event) if Driver::is_relevant(&event, arg, extensions.as_ref()) => break,
+          Ok(_) => (),
+        }
+      }
+      // Editors often write several times per save, so let the events settle
+      thread::sleep(time::Duration::from_millis(100));
+      while receiver.try_recv().is_ok() {}
+    }
+  }
+
+  fn is_relevant(
+    event: &notify::Event,
+    arg: &cli::Arg,
+    extensions: Option<&language::Extensions>,
+  ) -> bool {
+    !event.kind.is_access()
+      && event.paths.iter().any(|path| {
+        path.ends_with(config::FILE)
+          || extensions.is_some_and(|extensions| extensions.grammar(path).is_some())
+          || arg
+            .compile_commands
+            .as_ref()
+            .is_some_and(|database| path.ends_with(database))
+      })
+  }
+
+  fn extensions(arg: &cli::Arg) -> Result<language::Extensions> {
+    let config = config::Config::load(&arg.paths[0].path)?;
+    language::Extensions::new(&config.extensions, &arg.extensions)
+  }
+
+  fn render<T: analyzer::Analyzer>(
+    analyzer: &T,
+    arg: &cli::Arg,
+    cache: Option<&mut cache::Cache<T::Summary>>,
+  ) -> Result<String> {
+    let encoding = match arg.encoding {
+      Some(ref label) => Some(
+        encoding_rs::Encoding::for_label(label.as_bytes())
+          .with_context(|| format!("Unknown encoding: \"{}\"!", label))?,
+      ),
+      None => None,
+    };
+    let revision = match arg.rev {
+      Some(ref rev) => Some(git::Revision::new(
+        &arg
+          .paths
+          .iter()
+          .map(|root| root.path.as_path())
+          .collect::<Vec<_>>(),
+        rev,
+      )?),
+      None => None,
+    };
+    if let Some(ref revision) = revision {
+      analyzer.set_revision_files(&revision.paths().collect::<Vec<_>>());
+    }
+    let extensions = Driver::extensions(arg)?;
+
+    // Files under several roots are analyzed once, as part of the first one. Those of labeled
+    // roots are shown relative to their root, after its label.
+    let mut files = vec![];
+    let mut visited = HashSet::new();
+    let mut labels = HashMap::new();
+    for (i, root) in arg.paths.iter().enumerate() {
+      let root_files = match (&arg.compile_commands, &revision) {
+        (Some(database), _) => {
+          compdb::load(database, &root.path, &arg.exclude_globset, &extensions)?
+        }
+        (None, Some(revision)) => revision.files(
+          i,
+          &arg.exclude_globset,
+          arg.no_exclude,
+          arg.no_ignore,
+          &extensions,
+        )?,
+        (None, None) => pathwalk::FileIterator::new(
+          &root.path,
+          &arg.exclude_globset,
+          arg.no_exclude,
+          arg.no_ignore,
+          &extensions,
+          arg.follow_symlinks,
+        )?
+        .map(|(file, grammar)| compdb::CompileCommand {
+          file,
+          grammar,
+          flags: compdb::CompileFlags::default(),
+        })
+        .collect(),
+      };
+      let base = match revision {
+        Some(ref revision) => revision.prefix(i),
+        None => root.path.as_path(),
+      };
+      for command in root_files {
+        let key = match revision {
+          Some(_) => command.file.clone(),
+          None => command
+            .file
+            .canonicalize()
+            .unwrap_or_else(|_| command.file.clone()),
+        };
+        if !visited.insert(key) {
+          continue;
+        }
+        if let Some(ref label) = root.label {
+          let relative_path = command
+            .file
+            .strip_prefix(base)
+            .ok()
+            .filter(|relative_path| !relative_path.as_os_str().is_empty())
+            .or(command.file.file_name().map(path::Path::new))
+            .unwrap_or(&command.file);
+          labels.insert(
+            command.file.display().to_string(),
+            (label.clone(), relative_path.display().to_string()),
+          );
+        }
+        files.push(command);
+      }
+    }
+
+    if extensions.c_headers(
+      files
+        .iter()
+        .map(|command| (command.file.as_path(), command.grammar)),
+    ) {
+      files
+        .iter_mut()
+        .filter(|command| command.file.extension().is_some_and(|ext| ext == "h"))
+        .filter(|command| {
+          let source = match revision {
+            Some(ref revision) => revision.read(&command.file).ok(),
+            None => fs::read(&command.file).ok(),
+          };
+          source.is_none_or(|source| !language::uses_cpp(&String::from_utf8_lossy(&source)))
+        })
+        .for_each(|command| command.grammar = language::Grammar::C);
+    }
+
+    files.retain(|command| analyzer.accepts(command.grammar));
+
+    // Macros and include directories given on the command line take precedence over the
+    // compilation database
+    for command in files.iter_mut() {
+      arg
+        .defines
+        .iter()
+        .for_each(|definition| command.flags.define(definition));
+      arg
+        .undefines
+        .iter()
+        .for_each(|name| command.flags.undefine(name));
+      command
+        .flags
+        .include_paths
+        .splice(0..0, arg.include_dirs.iter().cloned());
+    }
+
+    let pool = rayon::ThreadPoolBuilder::new()
+      .num_threads(arg.jobs)
+      .build()?;
+    let (mut graph, parse_errors) = pool.install(|| {
+      let reader = Reader {
+        revision: revision.as_ref(),
+        encoding,
+      };
+      Driver::extract(analyzer, arg, files, &reader, cache)
+    });
+    if let Some(max_errors) = arg.strict {
+      if parse_errors > max_errors {
+        return Err(anyhow::anyhow!(
+          "Too many parse errors ({} > {})!",
+          parse_errors,
+          max_errors
+        ));
       }
-      Ok(files) => {
-        let syntax_trees = files
-          .map(|file| syntaxtree::SyntaxTree::new(file, &arg.ignore_macros))
-          .collect::<Vec<_>>();
-
-        let mut graph = graph::Graph::new();
-        syntax_trees.iter().for_each(|tree| match tree {
-          Err(e) => eprintln!("[Error] {}", e),
-          Ok(tree) => analyzer.extract_nodes(tree, &mut graph),
-        });
-        syntax_trees.into_iter().for_each(|ref tree| match tree {
-          Err(e) => eprintln!("[Error] {}", e),
-          Ok(tree) => analyzer.extract_edges(tree, &mut graph),
-        });
-
-        if arg.reverse {
-          graph = graph.reverse();
+    }
+
+    if !labels.is_empty() {
+      graph = graph.map_locations(|loc| {
+        if let Some((label, file)) = labels.get(&loc.file) {
+          loc.label = Some(label.clone());
+          loc.file = file.clone();
         }
+      });
+    }
+    if arg.reverse {
+      graph = graph.reverse();
+    }
+
+    let display = display::Display::new(
+      &graph,
+      &arg.patterns,
+      arg.succinct,
+      arg.color,
+      arg.depth,
+      arg.ignore_unknown,
+      arg.sort_children,
+    );
+    Ok(if arg.dot {
+      display.to_dot()
+    } else {
+      display.to_text()
+    })
+  }
 
-        let display = display::Display::new(
-          &graph,
-          &arg.patterns,
-          arg.succinct,
-          arg.color,
-          arg.depth,
-          arg.ignore_unknown,
-          arg.sort_children,
+  // Each phase runs in parallel, with every file extracted into its own partial graph.
+  // Partial graphs are merged in file order, so the result doesn't depend on the thread count.
+  fn extract<T: analyzer::Analyzer>(
+    analyzer: &T,
+    arg: &cli::Arg,
+    files: Vec<compdb::CompileCommand>,
+    reader: &Reader,
+    mut cache: Option<&mut cache::Cache<T::Summary>>,
+  ) -> (graph::Graph, usize) {
+    let mut preprocessor = preprocessor::Preprocessor::new(&arg.ignore_macros);
+    let mut macros_fingerprint = String::new();
+    if arg.expand_macros {
+      let macros = files
+        .par_iter()
+        .filter(|command| command.grammar.preprocessed())
+        .map(|command| {
+          let mut macros = preprocessor::expansion::MacroTable::new();
+          if let Ok(source) = reader.read(&command.file) {
+            macros.collect(&preprocessor::conditional::evaluate(
+              &source,
+              &command.flags,
+            ));
+          }
+          macros
+        })
+        .reduce(
+          preprocessor::expansion::MacroTable::new,
+          |mut macros, other| {
+            macros.merge(other);
+            macros
+          },
         );
-        if arg.dot {
-          display.to_dot()
-        } else {
-          display.to_text()
+      macros_fingerprint = macros.fingerprint();
+      preprocessor.set_macros(macros);
+    }
+
+    if let Some(cache) = cache.as_mut() {
+      cache.validate(&[
+        analyzer.name(),
+        &arg.ignore_macros.join("\n"),
+        &arg.expand_macros.to_string(),
+        &macros_fingerprint,
+      ]);
+    }
+
+    let units = files
+      .into_par_iter()
+      .map(|command| {
+        Driver::extract_nodes(analyzer, &preprocessor, reader, cache.as_deref(), command)
+      })
+      .collect::<Vec<_>>();
+    let mut units = units
+      .into_iter()
+      .filter_map(|unit| match unit {
+        Err(e) => {
+          eprintln!("[Error] {}", e);
+          None
         }
+        Ok(unit) => Some(unit),
+      })
+      .collect::<Vec<_>>();
+
+    let mut graph = graph::Graph::new();
+    let mut parse_errors = 0;
+    for unit in units.iter() {
+      graph.merge(&unit.entry.nodes);
+      Driver::report(&unit.command.file, &unit.entry.errors);
+      parse_errors += unit.entry.errors.len();
+    }
+
+    // Cached edges are only reused if no file has changed its nodes, nor what is linked across
+    // files
+    let fingerprint = analyzer.link(units.iter().map(|unit| &unit.entry.summary));
+    let nodes_hash = if cache.is_some() {
+      cache::hash_nodes(&graph, &fingerprint)
+    } else {
+      String::new()
+    };
+    units.par_iter_mut().for_each(|unit| {
+      if matches!(unit.entry.edges, Some((ref hash, _)) if *hash == nodes_hash) {
+        return;
+      }
+      let mut partial_graph = graph::Graph::new();
+      analyzer.extract_edges(&unit.entry.summary, &graph, &mut partial_graph);
+      unit.entry.edges = Some((nodes_hash.clone(), partial_graph));
+    });
+    for unit in units.iter() {
+      if let Some((_, ref edges)) = unit.entry.edges {
+        graph.merge(edges);
+      }
+    }
+
+    if let Some(cache) = cache {
+      let entries = units
+        .into_iter()
+        .map(|unit| (unit.command.file.display().to_string(), unit.entry))
+        .collect();
+      if let Err(e) = cache.update(entries) {
+        eprintln!("[Warning] {}", e);
+      }
+    }
+    (graph, parse_errors)
+  }
+
+  // Summarize the parse errors of a file, since the definitions they cover are silently missing
+  fn report(file: &path::Path, errors: &[syntaxtree::ParseError]) {
+    if errors.is_empty() {
+      return;
+    }
+    let mut rows = errors
+      .iter()
+      .take(5)
+      .map(|error| format!("+{}", error.row))
+      .collect::<Vec<_>>();
+    if errors.len() > rows.len() {
+      rows.push(String::from("..."));
+    }
+    let mut candidates = Vec::<&str>::new();
+    for candidate in errors.iter().filter_map(|error| error.candidate.as_deref()) {
+      if !candidates.contains(&candidate) {
+        candidates.push(candidate);
       }
     }
+    let suggestion = if candidates.is_empty() {
+      String::new()
+    } else {
+      format!(
+        ", try {}",
+        candidates
+          .iter()
+          .take(3)
+          .map(|candidate| format!("--ignore-macro \"{}\"", candidate))
+          .collect::<Vec<_>>()
+          .join(" ")
+      )
+    };
+    eprintln!(
+      "[Warning] {} parse error(s) in {} at {}{}",
+      errors.len(),
+      file.display(),
+      rows.join(" "),
+      suggestion
+    );
+  }
+
+  // Parse a file and extract its nodes, unless the cache already holds them
+  fn extract_nodes<T: analyzer::Analyzer>(
+    analyzer: &T,
+    preprocessor: &preprocessor::Preprocessor,
+    reader: &Reader,
+    cache: Option<&cache::Cache<T::Summary>>,
+    command: compdb::CompileCommand,
+  ) -> Result<Unit<T::Summary>> {
+    let source = reader.read(&command.file)?;
+    let hash = match cache {
+      Some(cache) => {
+        let hash = cache::hash_file(&source, &command);
+        if let Some(entry) = cache.get(&command.file.display().to_string(), &hash) {
+          return Ok(Unit {
+            command,
+            entry: entry.clone(),
+          });
+        }
+        hash
+      }
+      None => String::new(),
+    };
+
+    let syntax_tree = syntaxtree::SyntaxTree::new(
+      command.file.clone(),
+      command.grammar,
+      source,
+      &command.flags,
+      preprocessor,
+    )?;
+    let mut nodes = graph::Graph::new();
+    analyzer.extract_nodes(&syntax_tree, &mut nodes);
+    Ok(Unit {
+      command,
+      entry: cache::Entry {
+        hash,
+        errors: syntax_tree.errors(),
+        nodes,
+        summary: analyzer.summarize(&syntax_tree),
+        edges: None,
+      },
+    })
+  }
+}
+
+impl<'a> Reader<'a> {
+  fn read(&self, file: &path::Path) -> Result<String> {
+    let bytes = match self.revision {
+      Some(revision) => revision.read(file)?,
+      None => fs::read(file).with_context(|| format!("Failed to read: {}!", file.display()))?,
+    };
+    Ok(syntaxtree::decode(bytes, self.encoding))
   }
 }
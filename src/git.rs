@@ -0,0 +1,293 @@
+use crate::compdb;
+use crate::language;
+use crate::pathwalk;
+
+use anyhow::{Context, Result};
+use ignore::gitignore::Gitignore;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path;
+use std::process;
+use std::sync::Mutex;
+
+// A `git cat-file --batch` process, which prints the content of each object named on its stdin
+struct Batch {
+  child: process::Child,
+  stdin: Option<process::ChildStdin>,
+  stdout: io::BufReader<process::ChildStdout>,
+}
+
+// The files of a commit, read from the object database rather than the working tree
+pub struct Revision {
+  toplevel: path::PathBuf,
+  // Repository-relative path of every file of the commit, to its blob id
+  blobs: HashMap<path::PathBuf, String>,
+  // Repository-relative path of each analyzed path
+  prefixes: Vec<path::PathBuf>,
+  batch: Mutex<Batch>,
+}
+
+fn git(dir: &path::Path, args: &[&str]) -> Result<Vec<u8>> {
+  let output = process::Command::new("git")
+    .arg("-C")
+    .arg(dir)
+    .args(args)
+    .output()
+    .context("Failed to run git!")?;
+  if !output.status.success() {
+    return Err(anyhow::anyhow!(
+      "git {} failed: {}",
+      args.join(" "),
+      String::from_utf8_lossy(&output.stderr).trim()
+    ));
+  }
+  Ok(output.stdout)
+}
+
+// The repository containing `path` and the location of `path` in it. `path` may no longer exist in
+// the working tree, so the repository is found from the closest existing directory above it.
+fn locate(path: &path::Path) -> Result<(path::PathBuf, path::PathBuf)> {
+  let dir = path
+    .ancestors()
+    .find(|dir| dir.is_dir() || dir.as_os_str().is_empty())
+    .unwrap_or(path);
+  let rest = path.strip_prefix(dir).unwrap_or(path::Path::new(""));
+  let dir = if dir.as_os_str().is_empty() {
+    path::Path::new(".")
+  } else {
+    dir
+  };
+
+  let toplevel = String::from_utf8(git(dir, &["rev-parse", "--show-toplevel"])?)?;
+  let prefix = String::from_utf8(git(dir, &["rev-parse", "--show-prefix"])?)?;
+  Ok((
+    path::PathBuf::from(toplevel.trim_end()),
+    path::Path::new(prefix.trim_end()).join(rest),
+  ))
+}
+
+impl Revision {
+  // Every path must be in the same repository
+  pub fn new(paths: &[&path::Path], rev: &str) -> Result<Self> {
+    let mut toplevel = None;
+    let mut prefixes = vec![];
+    for path in paths {
+      let (path_toplevel, prefix) = locate(path)?;
+      match toplevel {
+        Some(ref toplevel) if *toplevel != path_toplevel => {
+          return Err(anyhow::anyhow!(
+            "\"{}\" is not in the repository of \"{}\"!",
+            path.display(),
+            paths[0].display()
+          ));
+        }
+        Some(_) => (),
+        None => toplevel = Some(path_toplevel),
+      }
+      prefixes.push(prefix);
+    }
+    let toplevel = toplevel.context("No path to analyze!")?;
+
+    let commit = format!("{}^{{commit}}", rev);
+    git(&toplevel, &["rev-parse", "--verify", "--quiet", &commit])
+      .with_context(|| format!("Unknown revision: \"{}\"!", rev))?;
+    // Files outside the analyzed paths may still be included, or hold ignore rules
+    let blobs = git(&toplevel, &["ls-tree", "-r", "-z", "--full-tree", rev])?
+      .split(|&byte| byte == 0)
+      .filter_map(|entry| {
+        // <mode> SP <type> SP <object> TAB <file>
+        let entry = std::str::from_utf8(entry).ok()?;
+        let (info, file) = entry.split_once('\t')?;
+        match info.split(' ').collect::<Vec<_>>()[..] {
+          [mode, "blob", object] if mode != "120000" => {
+            Some((path::PathBuf::from(file), String::from(object)))
+          }
+          _ => None,
+        }
+      })
+      .collect::<HashMap<_, _>>();
+    if let Some(prefix) = prefixes
+      .iter()
+      .find(|prefix| !blobs.keys().any(|file| file.starts_with(prefix)))
+    {
+      return Err(anyhow::anyhow!(
+        "Failed to read: \"{}\" at {}!",
+        prefix.display(),
+        rev
+      ));
+    }
+
+    let mut child = process::Command::new("git")
+      .arg("-C")
+      .arg(&toplevel)
+      .args(["cat-file", "--batch"])
+      .stdin(process::Stdio::piped())
+      .stdout(process::Stdio::piped())
+      .spawn()
+      .context("Failed to run git!")?;
+    let batch = Batch {
+      stdin: child.stdin.take(),
+      stdout: io::BufReader::new(child.stdout.take().context("Failed to run git!")?),
+      child,
+    };
+
+    Ok(Revision {
+      toplevel,
+      blobs,
+      prefixes,
+      batch: Mutex::new(batch),
+    })
+  }
+
+  pub fn prefix(&self, root: usize) -> &path::Path {
+    &self.prefixes[root]
+  }
+
+  // Every file of the commit, named relative to the repository
+  pub fn paths(&self) -> impl Iterator<Item = &path::Path> {
+    self.blobs.keys().map(path::PathBuf::as_path)
+  }
+
+  // The ignore rules of each directory of the commit, along with those of `.git/info/exclude` in
+  // the working tree
+  fn ignores(&self) -> HashMap<&path::Path, Gitignore> {
+    let mut ignore_files = HashMap::<&path::Path, Vec<(usize, path::PathBuf, String)>>::new();
+    for file in self.blobs.keys() {
+      let (dir, name) = match (file.parent(), file.file_name()) {
+        (Some(dir), Some(name)) => (dir, name),
+        _ => continue,
+      };
+      if let Some(order) = pathwalk::IGNORE_FILES.iter().position(|file| *file == name) {
+        if let Ok(content) = self.read(file) {
+          ignore_files.entry(dir).or_default().push((
+            order,
+            file.clone(),
+            String::from_utf8_lossy(&content).into_owned(),
+          ));
+        }
+      }
+    }
+    let exclude = self.toplevel.join(pathwalk::IGNORE_FILES[0]);
+    if let Ok(content) = fs::read_to_string(&exclude) {
+      ignore_files
+        .entry(path::Path::new(""))
+        .or_default()
+        .push((0, exclude, content));
+    }
+
+    ignore_files
+      .into_iter()
+      .filter_map(|(dir, mut files)| {
+        files.sort_by_key(|(order, _, _)| *order);
+        let files = files
+          .into_iter()
+          .map(|(_, file, content)| (file, content))
+          .collect::<Vec<_>>();
+        Some((
+          dir,
+          pathwalk::build_ignore(&self.toplevel.join(dir), &files)?,
+        ))
+      })
+      .collect()
+  }
+
+  // The files under the `root`-th analyzed path, with the same exclusions as
+  // `pathwalk::FileIterator`, ignore files being read from the commit. Files are named relative to
+  // the repository, so they don't depend on the working directory.
+  pub fn files(
+    &self,
+    root: usize,
+    exclude_globset: &[String],
+    no_exclude: bool,
+    no_ignore: bool,
+    extensions: &language::Extensions,
+  ) -> Result<Vec<compdb::CompileCommand>> {
+    let exclude_globset = pathwalk::build_globset(exclude_globset)?;
+    let exclude_dirs = pathwalk::build_exclude_dirs(no_exclude)?;
+    let ignores = if no_ignore {
+      HashMap::new()
+    } else {
+      self.ignores()
+    };
+    // The rules of the deepest directory that mentions the path win
+    let is_ignored = |file: &path::Path, is_dir: bool| {
+      let path = self.toplevel.join(file);
+      file
+        .ancestors()
+        .skip(1)
+        .filter_map(|dir| ignores.get(dir))
+        .map(|ignore| ignore.matched(&path, is_dir))
+        .find(|matched| !matched.is_none())
+        .is_some_and(|matched| matched.is_ignore())
+    };
+    let prefix = &self.prefixes[root];
+    let mut files = self
+      .blobs
+      .keys()
+      .filter_map(|file| Some((file, file.strip_prefix(prefix).ok()?)))
+      .filter(|(file, relative_path)| {
+        exclude_globset.matches(relative_path).is_empty()
+          && !relative_path
+            .parent()
+            .into_iter()
+            .flat_map(path::Path::iter)
+            .any(|dir| pathwalk::is_hidden(dir) || exclude_dirs.is_match(dir))
+          && !is_ignored(file, false)
+          && !file
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(prefix) && *dir != prefix.as_path())
+            .any(|dir| is_ignored(dir, true))
+      })
+      .filter_map(|(file, _)| {
+        let grammar = extensions.grammar_with(file, || {
+          self.read(file).ok().map(|mut head| {
+            head.truncate(4096);
+            head
+          })
+        })?;
+        Some(compdb::CompileCommand {
+          file: file.clone(),
+          grammar,
+          flags: compdb::CompileFlags::default(),
+        })
+      })
+      .collect::<Vec<_>>();
+    files.sort_by(|a, b| a.file.cmp(&b.file));
+    Ok(files)
+  }
+
+  pub fn read(&self, file: &path::Path) -> Result<Vec<u8>> {
+    let object = self
+      .blobs
+      .get(file)
+      .with_context(|| format!("Failed to read: {}!", file.display()))?;
+    let mut batch = self.batch.lock().unwrap();
+    let Batch { stdin, stdout, .. } = &mut *batch;
+    let stdin = stdin.as_mut().context("Failed to run git!")?;
+    writeln!(stdin, "{}", object)?;
+    stdin.flush()?;
+
+    // <object> SP <type> SP <size> LF <content> LF
+    let mut header = String::new();
+    stdout.read_line(&mut header)?;
+    let size = header
+      .split_whitespace()
+      .nth(2)
+      .and_then(|size| size.parse::<usize>().ok())
+      .with_context(|| format!("Failed to read: {}!", file.display()))?;
+    let mut content = vec![0; size + 1];
+    stdout.read_exact(&mut content)?;
+    content.pop();
+    Ok(content)
+  }
+}
+
+impl Drop for Batch {
+  fn drop(&mut self) {
+    // Closing stdin lets git exit
+    self.stdin.take();
+    let _ = self.child.wait();
+  }
+}
@@ -0,0 +1,186 @@
+use crate::analyzer;
+use crate::graph;
+use crate::node;
+use crate::syntaxtree;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+type Call = node::Node;
+
+pub struct CallAnalyzer {
+  method_pool: OnceLock<HashMap<String, Call>>,
+}
+
+// The calls made by the functions of a file, in the order they are found
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Calls {
+  calls: Vec<(String, Callee)>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+enum Callee {
+  // e.g., `f()` or `fmt.Println()`, by its qualified name and as written (e.g., `f()` may be a
+  // builtin)
+  Function(String, String),
+  // e.g., `x.f()`, along with the type of `x` if it is the receiver
+  Method(Option<String>, String),
+}
+
+impl CallAnalyzer {
+  pub fn new() -> Self {
+    CallAnalyzer {
+      method_pool: OnceLock::new(),
+    }
+  }
+}
+
+// The function or the method declared by a node, e.g., `http.Server.Serve()`
+fn function_name(
+  syntax_tree: &syntaxtree::SyntaxTree,
+  package: &super::Package,
+  node: &tree_sitter::Node,
+) -> Option<String> {
+  let name = syntax_tree.source(&node.child_by_field_name("name")?);
+  match node.kind() {
+    "function_declaration" => Some(format!("{}()", package.qualify(name))),
+    "method_declaration" => {
+      let (receiver, _) = super::receiver(syntax_tree, node)?;
+      Some(format!("{}.{}()", package.qualify(&receiver), name))
+    }
+    _ => None,
+  }
+}
+
+impl analyzer::Analyzer for CallAnalyzer {
+  type Summary = Calls;
+
+  fn name(&self) -> &'static str {
+    "call"
+  }
+
+  fn extract_nodes(&self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph) {
+    let package = super::Package::new(syntax_tree);
+    for node in syntax_tree.iter() {
+      if let (Some(function), Some(name)) = (
+        function_name(syntax_tree, &package, &node),
+        node.child_by_field_name("name"),
+      ) {
+        graph.add_node(&Call::new(
+          &function,
+          &syntax_tree.file,
+          name.start_position().row + 1,
+        ));
+      }
+    }
+  }
+
+  // Selectors on an imported package (e.g., `fmt.Println()`) call functions, and those on the
+  // receiver call methods of its type
+  fn summarize(&self, syntax_tree: &syntaxtree::SyntaxTree) -> Calls {
+    let package = super::Package::new(syntax_tree);
+    let mut calls = vec![];
+    // The function being walked, along with the byte where it ends and its receiver
+    let mut caller = None::<(usize, String, Option<(String, &str)>)>;
+    for node in syntax_tree.iter() {
+      if caller
+        .as_ref()
+        .is_some_and(|(end, _, _)| node.start_byte() >= *end)
+      {
+        caller = None;
+      }
+      if let Some(function) = function_name(syntax_tree, &package, &node) {
+        let receiver = super::receiver(syntax_tree, &node)
+          .and_then(|(ty, name)| Some((package.qualify(&ty), name?)));
+        caller = Some((node.end_byte(), function, receiver));
+        continue;
+      }
+      let (caller, receiver) = match (node.kind(), &caller) {
+        ("call_expression", Some((_, caller, receiver))) => (caller, receiver),
+        _ => continue,
+      };
+      let function = match node.child_by_field_name("function") {
+        Some(function) => function,
+        None => continue,
+      };
+      let callee = match function.kind() {
+        "identifier" => {
+          let name = syntax_tree.source(&function);
+          Callee::Function(package.qualify(name), String::from(name))
+        }
+        "selector_expression" => {
+          let (operand, field) = match (
+            function.child_by_field_name("operand"),
+            function.child_by_field_name("field"),
+          ) {
+            (Some(operand), Some(field)) => (operand, syntax_tree.source(&field)),
+            _ => continue,
+          };
+          let operand =
+            Some(syntax_tree.source(&operand)).filter(|_| operand.kind() == "identifier");
+          match (operand, receiver) {
+            (Some(operand), Some((ty, name))) if operand == *name => {
+              Callee::Method(Some(ty.clone()), String::from(field))
+            }
+            (Some(operand), _) if package.imports.contains_key(operand) => {
+              let function = format!("{}.{}", package.imports[operand], field);
+              Callee::Function(function.clone(), function)
+            }
+            _ => Callee::Method(None, String::from(field)),
+          }
+        }
+        _ => continue,
+      };
+      calls.push((caller.clone(), callee));
+    }
+    Calls { calls }
+  }
+
+  fn extract_edges(&self, calls: &Calls, graph: &graph::Graph, partial_graph: &mut graph::Graph) {
+    // Methods are named after their package and type (e.g., `http.Server.Serve()`), functions only
+    // after their package
+    let method_pool = self.method_pool.get_or_init(|| {
+      let mut method_pool = HashMap::<String, Call>::new();
+      for (qualified_name, call) in graph.nodes.iter() {
+        let mut names = qualified_name.rsplitn(3, '.');
+        if let (Some(name), Some(_), Some(_)) = (names.next(), names.next(), names.next()) {
+          method_pool
+            .entry(String::from(name))
+            .and_modify(|method| method.merge_node(call))
+            .or_insert_with(|| call.clone());
+        }
+      }
+      method_pool
+    });
+
+    for (caller, callee) in calls.calls.iter() {
+      let call = match graph.get_node(caller) {
+        Some(call) => call,
+        None => continue,
+      };
+      let (callee, name) = match callee {
+        Callee::Function(function, name) => (
+          graph.get_node(&format!("{}()", function)),
+          format!("{}()", name),
+        ),
+        Callee::Method(ty, name) => {
+          let method = format!("{}()", name);
+          let callee = ty
+            .as_ref()
+            .and_then(|ty| graph.get_node(&format!("{}.{}", ty, method)))
+            .or_else(|| method_pool.get(&method));
+          (callee, method)
+        }
+      };
+      match callee {
+        Some(callee) => partial_graph.add_edge(call, callee),
+        None => {
+          let callee = Call::new_without_loc(&name);
+          partial_graph.add_node(&callee);
+          partial_graph.add_edge(call, &callee);
+        }
+      }
+    }
+  }
+}
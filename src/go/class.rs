@@ -0,0 +1,406 @@
+use crate::analyzer;
+use crate::graph;
+use crate::node;
+use crate::syntaxtree;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::OnceLock;
+
+type Class = node::Node;
+
+pub struct ClassAnalyzer {
+  // From a type to the interfaces it satisfies, as found by `link`
+  interfaces: OnceLock<HashMap<String, Vec<String>>>,
+}
+
+// The types declared in a file, by their qualified name
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Types {
+  // Embedded types (e.g., `struct { Base }` or `interface { io.Reader }`) as base classes
+  inheritances: Vec<analyzer::Inheritance>,
+  // Interfaces along with the signatures of their methods
+  interfaces: Vec<(String, Vec<String>)>,
+  // Other types, which may satisfy interfaces
+  types: Vec<String>,
+  // Methods along with the type of their receiver
+  methods: Vec<(String, String)>,
+}
+
+impl ClassAnalyzer {
+  pub fn new() -> Self {
+    ClassAnalyzer {
+      interfaces: OnceLock::new(),
+    }
+  }
+}
+
+// A method as compared between types and interfaces, e.g., `Read([]byte)(int,error)`. Types are
+// compared without their package, which may be written differently in different files.
+fn signature(syntax_tree: &syntaxtree::SyntaxTree, method: &tree_sitter::Node) -> Option<String> {
+  static PACKAGE: OnceLock<Regex> = OnceLock::new();
+  let package = PACKAGE.get_or_init(|| Regex::new(r"\b[A-Za-z_][A-Za-z0-9_]*\.").unwrap());
+  let types = |node: Option<tree_sitter::Node>| {
+    let node = match node {
+      Some(node) => node,
+      None => return String::new(),
+    };
+    if node.kind() != "parameter_list" {
+      return String::from(syntax_tree.source(&node));
+    }
+    // `a, b int` has two parameters of type `int`
+    let mut cursor = node.walk();
+    let types = node
+      .named_children(&mut cursor)
+      .filter_map(|parameter| {
+        let ty = syntax_tree.source(&parameter.child_by_field_name("type")?);
+        let ty = match parameter.kind() {
+          "variadic_parameter_declaration" => format!("...{}", ty),
+          _ => String::from(ty),
+        };
+        let mut cursor = parameter.walk();
+        let count = parameter
+          .children_by_field_name("name", &mut cursor)
+          .count()
+          .max(1);
+        Some(vec![ty; count])
+      })
+      .flatten()
+      .collect::<Vec<_>>();
+    format!("({})", types.join(","))
+  };
+  let signature = format!(
+    "{}{}{}",
+    syntax_tree.source(&method.child_by_field_name("name")?),
+    types(method.child_by_field_name("parameters")),
+    types(method.child_by_field_name("result"))
+  );
+  let signature = signature
+    .chars()
+    .filter(|c| !c.is_whitespace())
+    .collect::<String>();
+  Some(package.replace_all(&signature, "").into_owned())
+}
+
+// The methods of a type, including those promoted from the types it embeds. Types declared outside
+// of the project (e.g., `io.Reader`) stand for their unknown methods, so an interface embedding one
+// is only satisfied by the types embedding it as well.
+fn method_set_of<'a>(
+  ty: &'a str,
+  methods: &HashMap<&'a str, HashSet<&'a str>>,
+  embedded: &HashMap<&'a str, Vec<&'a str>>,
+  method_sets: &mut HashMap<&'a str, HashSet<&'a str>>,
+) -> HashSet<&'a str> {
+  if let Some(method_set) = method_sets.get(ty) {
+    return method_set.clone();
+  }
+  if !methods.contains_key(ty) {
+    return HashSet::from([ty]);
+  }
+  // Embedding cycles are invalid, but shouldn't recurse forever
+  method_sets.insert(ty, HashSet::new());
+  let mut method_set = methods.get(ty).cloned().unwrap_or_default();
+  for baseclass in embedded.get(ty).into_iter().flatten() {
+    method_set.extend(method_set_of(baseclass, methods, embedded, method_sets));
+  }
+  method_sets.insert(ty, method_set.clone());
+  method_set
+}
+
+impl analyzer::Analyzer for ClassAnalyzer {
+  type Summary = Types;
+
+  fn name(&self) -> &'static str {
+    "class"
+  }
+
+  fn extract_nodes(&self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph) {
+    let package = super::Package::new(syntax_tree);
+    for node in syntax_tree.iter() {
+      let new_class = match node.kind() {
+        "type_spec" => Class::new,
+        "type_alias" => Class::new_alias,
+        _ => continue,
+      };
+      if let Some(name) = node.child_by_field_name("name") {
+        graph.add_node(&new_class(
+          &package.qualify(syntax_tree.source(&name)),
+          &syntax_tree.file,
+          name.start_position().row + 1,
+        ));
+      }
+    }
+  }
+
+  fn summarize(&self, syntax_tree: &syntaxtree::SyntaxTree) -> Types {
+    let package = super::Package::new(syntax_tree);
+    let mut summary = Types {
+      inheritances: vec![],
+      interfaces: vec![],
+      types: vec![],
+      methods: vec![],
+    };
+    for node in syntax_tree.iter() {
+      if node.kind() == "method_declaration" {
+        if let (Some((receiver, _)), Some(signature)) = (
+          super::receiver(syntax_tree, &node),
+          signature(syntax_tree, &node),
+        ) {
+          summary
+            .methods
+            .push((package.qualify(&receiver), signature));
+        }
+        continue;
+      }
+      let (name, ty) = match (
+        node.kind(),
+        node.child_by_field_name("name"),
+        node.child_by_field_name("type"),
+      ) {
+        ("type_spec", Some(name), Some(ty)) => (package.qualify(syntax_tree.source(&name)), ty),
+        _ => continue,
+      };
+
+      // Embedded fields have no name, and embedded interfaces are constraints of a single type
+      let mut baseclasses = vec![];
+      let mut cursor = ty.walk();
+      match ty.kind() {
+        "struct_type" => {
+          let fields = ty
+            .named_children(&mut cursor)
+            .filter(|fields| fields.kind() == "field_declaration_list")
+            .collect::<Vec<_>>();
+          for fields in fields {
+            let mut cursor = fields.walk();
+            for field in fields.named_children(&mut cursor) {
+              if let (None, Some(ty)) = (
+                field.child_by_field_name("name"),
+                field.child_by_field_name("type"),
+              ) {
+                baseclasses.push(package.resolve(&super::type_name(syntax_tree, &ty)));
+              }
+            }
+          }
+          summary.types.push(name.clone());
+        }
+        "interface_type" => {
+          let mut methods = vec![];
+          for element in ty.named_children(&mut cursor) {
+            match element.kind() {
+              "method_spec" => methods.extend(signature(syntax_tree, &element)),
+              "constraint_elem" if element.named_child_count() == 1 => {
+                if let Some(ty) = element
+                  .named_child(0)
+                  .filter(|ty| matches!(ty.kind(), "type_identifier" | "qualified_type"))
+                {
+                  baseclasses.push(package.resolve(syntax_tree.source(&ty)));
+                }
+              }
+              _ => (),
+            }
+          }
+          summary.interfaces.push((name.clone(), methods));
+        }
+        _ => summary.types.push(name.clone()),
+      }
+      if !baseclasses.is_empty() {
+        summary.inheritances.push(analyzer::Inheritance {
+          class: name,
+          baseclasses,
+          attributes: vec![],
+        });
+      }
+    }
+    summary
+  }
+
+  // A type satisfies an interface if it has all of its methods, unless it does so through a type
+  // it embeds, which is shown as satisfying the interface instead
+  fn link<S: Borrow<Types>>(&self, summaries: impl Iterator<Item = S>) -> String {
+    let summaries = summaries.collect::<Vec<_>>();
+    let summaries = summaries.iter().map(S::borrow).collect::<Vec<_>>();
+    let mut methods = HashMap::<&str, HashSet<&str>>::new();
+    let mut embedded = HashMap::<&str, Vec<&str>>::new();
+    for summary in &summaries {
+      for ty in summary.types.iter() {
+        methods.entry(ty).or_default();
+      }
+      for (ty, method) in summary.methods.iter() {
+        methods.entry(ty).or_default().insert(method);
+      }
+      for (interface, interface_methods) in summary.interfaces.iter() {
+        methods
+          .entry(interface)
+          .or_default()
+          .extend(interface_methods.iter().map(String::as_str));
+      }
+      for inheritance in summary.inheritances.iter() {
+        embedded
+          .entry(&inheritance.class)
+          .or_default()
+          .extend(inheritance.baseclasses.iter().map(String::as_str));
+      }
+    }
+
+    let mut method_sets = HashMap::new();
+    // Interfaces are only checked against the types having their first method
+    let mut candidates = HashMap::<&str, Vec<(&str, HashSet<&str>)>>::new();
+    for summary in &summaries {
+      for (interface, _) in summary.interfaces.iter() {
+        let method_set = method_set_of(interface, &methods, &embedded, &mut method_sets);
+        if let Some(first) = method_set.iter().min().copied() {
+          candidates
+            .entry(first)
+            .or_default()
+            .push((interface, method_set));
+        }
+      }
+    }
+
+    let mut satisfied = BTreeMap::<&str, Vec<String>>::new();
+    for ty in summaries.iter().flat_map(|summary| summary.types.iter()) {
+      let method_set = method_set_of(ty, &methods, &embedded, &mut method_sets);
+      let mut interfaces = method_set
+        .iter()
+        .flat_map(|method| candidates.get(method).into_iter().flatten())
+        .filter(|(_, interface_methods)| interface_methods.is_subset(&method_set))
+        .map(|(interface, _)| *interface)
+        .collect::<Vec<_>>();
+      interfaces.sort();
+      satisfied.insert(ty, interfaces.iter().map(|i| String::from(*i)).collect());
+    }
+    let satisfied = satisfied
+      .iter()
+      .map(|(ty, interfaces)| {
+        let baseclasses = embedded.get(ty).cloned().unwrap_or_default();
+        let interfaces = interfaces
+          .iter()
+          .filter(|interface| {
+            !baseclasses.iter().any(|baseclass| {
+              *baseclass == interface.as_str()
+                || satisfied
+                  .get(baseclass)
+                  .is_some_and(|interfaces| interfaces.contains(interface))
+            })
+          })
+          .cloned()
+          .collect::<Vec<_>>();
+        (String::from(*ty), interfaces)
+      })
+      .filter(|(_, interfaces)| !interfaces.is_empty())
+      .collect::<HashMap<_, _>>();
+
+    let mut fingerprint = satisfied
+      .iter()
+      .map(|(ty, interfaces)| format!("{} {}", ty, interfaces.join(" ")))
+      .collect::<Vec<_>>();
+    fingerprint.sort();
+    let _ = self.interfaces.set(satisfied);
+    fingerprint.join("\n")
+  }
+
+  fn extract_edges(&self, types: &Types, graph: &graph::Graph, partial_graph: &mut graph::Graph) {
+    analyzer::extract_inheritance_edges(&types.inheritances, graph, partial_graph);
+    let interfaces = match self.interfaces.get() {
+      Some(interfaces) => interfaces,
+      None => return,
+    };
+    for ty in types.types.iter() {
+      if let (Some(class), Some(interfaces)) = (graph.get_node(ty), interfaces.get(ty)) {
+        for interface in interfaces
+          .iter()
+          .filter_map(|interface| graph.get_node(interface))
+        {
+          partial_graph.add_edge(interface, class);
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::analyzer::Analyzer;
+  use crate::compdb;
+  use crate::language;
+  use crate::preprocessor;
+
+  // The interfaces satisfied by each type of the given files
+  fn link(files: &[&str]) -> (HashMap<String, Vec<String>>, String) {
+    let analyzer = ClassAnalyzer::new();
+    let summaries = files
+      .iter()
+      .enumerate()
+      .map(|(i, source)| {
+        let syntax_tree = syntaxtree::SyntaxTree::new(
+          format!("{}.go", i).into(),
+          language::Grammar::Go,
+          String::from(*source),
+          &compdb::CompileFlags::default(),
+          &preprocessor::Preprocessor::new(&[]),
+        )
+        .unwrap();
+        analyzer.summarize(&syntax_tree)
+      })
+      .collect::<Vec<_>>();
+    let fingerprint = analyzer.link(summaries.iter());
+    (analyzer.interfaces.into_inner().unwrap(), fingerprint)
+  }
+
+  #[test]
+  fn satisfies_interfaces_by_method_set() {
+    let (interfaces, _) = link(&[
+      "package shapes\ntype Shape interface { Area() float64; Scale(f float64) }\n",
+      "package shapes\ntype Square struct{}\nfunc (s Square) Area() float64 { return 0 }\nfunc (s *Square) Scale(factor float64) {}\ntype Line struct{}\nfunc (l Line) Area() float64 { return 0 }\n",
+    ]);
+    assert_eq!(interfaces["shapes.Square"], ["shapes.Shape"]);
+    assert!(!interfaces.contains_key("shapes.Line"));
+  }
+
+  #[test]
+  fn satisfies_interfaces_through_embedding() {
+    let (interfaces, _) = link(&[
+      "package main\ntype Reader interface { Read() }\ntype Writer interface { Write() }\ntype ReadWriter interface { Reader; Writer }\ntype File struct{}\nfunc (f File) Read() {}\nfunc (f File) Write() {}\ntype Logged struct { File }\ntype Buffer struct{ File }\nfunc (b Buffer) Flush() {}\n",
+    ]);
+    assert_eq!(
+      interfaces["main.File"],
+      ["main.ReadWriter", "main.Reader", "main.Writer"]
+    );
+    // Shown through the embedded type instead
+    assert!(!interfaces.contains_key("main.Logged"));
+    assert!(!interfaces.contains_key("main.Buffer"));
+    // An interface embedding those it extends isn't shown as satisfying them
+    assert!(!interfaces.contains_key("main.ReadWriter"));
+  }
+
+  #[test]
+  fn needs_embedding_unknown_interfaces() {
+    let (interfaces, _) = link(&[
+      "package main\nimport \"io\"\ntype Closer interface { io.Reader; Close() }\ntype File struct{}\nfunc (f File) Close() {}\ntype Stream struct{ io.Reader }\nfunc (s Stream) Close() {}\n",
+    ]);
+    assert!(!interfaces.contains_key("main.File"));
+    assert_eq!(interfaces["main.Stream"], ["main.Closer"]);
+  }
+
+  #[test]
+  fn fingerprints_satisfied_interfaces() {
+    let shape = "package shapes\ntype Shape interface { Area() float64 }\n";
+    let (_, square) = link(&[
+      shape,
+      "package shapes\ntype Square struct{}\nfunc (s Square) Area() float64 { return 0 }\n",
+    ]);
+    let (_, resized) = link(&[
+      "package shapes\ntype Square struct{}\nfunc (s Square) Area() float64 { return 1 }\n",
+      shape,
+    ]);
+    let (_, other) = link(&[
+      shape,
+      "package shapes\ntype Square struct{}\nfunc (s Square) Area() int { return 0 }\n",
+    ]);
+    assert_eq!(square, resized);
+    assert_ne!(square, other);
+  }
+}
@@ -0,0 +1,125 @@
+pub mod call;
+pub mod class;
+
+use crate::analyzer;
+use crate::language::{self, Grammar};
+use crate::syntaxtree;
+
+use std::collections::HashMap;
+
+pub struct Go;
+
+impl language::Language for Go {
+  fn grammars(&self) -> &'static [(&'static str, Grammar)] {
+    &[("go", Grammar::Go)]
+  }
+
+  fn extensions(&self) -> &'static [(&'static str, Grammar)] {
+    &[("go", Grammar::Go)]
+  }
+
+  fn tree_sitter(&self, _grammar: Grammar) -> tree_sitter::Language {
+    tree_sitter_go::language()
+  }
+
+  fn analyzer(&self, kind: analyzer::Kind) -> Option<Box<dyn analyzer::AnyAnalyzer>> {
+    match kind {
+      analyzer::Kind::Call => Some(Box::new(call::CallAnalyzer::new())),
+      analyzer::Kind::Class => Some(Box::new(class::ClassAnalyzer::new())),
+      analyzer::Kind::Include => None,
+    }
+  }
+}
+
+// Functions and types are qualified by the name of their package (e.g., `http.Server`), and
+// packages are referred to by that name through the imports of a file
+struct Package {
+  name: String,
+  // From the name a package is imported as to its name
+  imports: HashMap<String, String>,
+}
+
+impl Package {
+  fn new(syntax_tree: &syntaxtree::SyntaxTree) -> Self {
+    let mut name = String::new();
+    let mut imports = HashMap::new();
+    for node in syntax_tree.iter() {
+      match node.kind() {
+        "package_clause" => {
+          if let Some(package) = node.named_child(0) {
+            name = String::from(syntax_tree.source(&package));
+          }
+        }
+        // e.g., `str "strings"`, or `"github.com/user/repo/v2"` for package `repo`
+        "import_spec" => {
+          let path = match node.child_by_field_name("path") {
+            Some(path) => syntax_tree.source(&path).trim_matches(['"', '`']),
+            None => continue,
+          };
+          let mut elements = path.rsplit('/');
+          let mut package = elements.next().unwrap_or(path);
+          if package.starts_with('v') && package[1..].parse::<u32>().is_ok() {
+            package = elements.next().unwrap_or(package);
+          }
+          let alias = match node.child_by_field_name("name") {
+            Some(alias) if alias.kind() == "package_identifier" => syntax_tree.source(&alias),
+            Some(_) => continue,
+            None => package,
+          };
+          imports.insert(String::from(alias), String::from(package));
+        }
+        "function_declaration" | "method_declaration" | "type_declaration" => break,
+        _ => (),
+      }
+    }
+    Package { name, imports }
+  }
+
+  fn qualify(&self, name: &str) -> String {
+    match self.name.as_str() {
+      "" => String::from(name),
+      package => format!("{}.{}", package, name),
+    }
+  }
+
+  // The qualified name of a type as written in this file, e.g., `Base` or `io.Reader`
+  fn resolve(&self, name: &str) -> String {
+    match name.split_once('.') {
+      Some((alias, name)) => format!(
+        "{}.{}",
+        self.imports.get(alias).map_or(alias, |package| package),
+        name
+      ),
+      None => self.qualify(name),
+    }
+  }
+}
+
+// The name of a type without pointers and type arguments, e.g., `List` for `*List[T]`
+fn type_name(syntax_tree: &syntaxtree::SyntaxTree, node: &tree_sitter::Node) -> String {
+  let source = syntax_tree.source(node).trim_start_matches(['*', ' ']);
+  let name = match source.find('[') {
+    Some(index) => &source[..index],
+    None => source,
+  };
+  String::from(name.trim())
+}
+
+// The type of the receiver of a method along with its name, if any, e.g., `Server` and `s` for
+// `func (s *Server) Serve()`
+fn receiver<'a>(
+  syntax_tree: &'a syntaxtree::SyntaxTree,
+  method: &tree_sitter::Node,
+) -> Option<(String, Option<&'a str>)> {
+  let receiver = method.child_by_field_name("receiver")?;
+  let mut cursor = receiver.walk();
+  let parameter = receiver
+    .named_children(&mut cursor)
+    .find(|parameter| parameter.kind() == "parameter_declaration")?;
+  Some((
+    type_name(syntax_tree, &parameter.child_by_field_name("type")?),
+    parameter
+      .child_by_field_name("name")
+      .map(|name| syntax_tree.source(&name)),
+  ))
+}
@@ -1,11 +1,49 @@
 use crate::node;
 
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct Graph {
   pub nodes: HashMap<String, node::Node>,
   pub edges: HashMap<node::Node, Vec<node::Node>>,
+  // Attributes of the edges having some, by the names of their nodes
+  pub attributes: HashMap<(String, String), EdgeAttributes>,
+}
+
+// How a class derives from a base class, in languages telling it (e.g., `protected virtual` in C++)
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EdgeAttributes {
+  pub access: Option<Access>,
+  pub is_virtual: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Access {
+  Public,
+  Protected,
+  Private,
+}
+
+impl std::fmt::Display for EdgeAttributes {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    let access = match self.access {
+      Some(Access::Public) => Some("public"),
+      Some(Access::Protected) => Some("protected"),
+      Some(Access::Private) => Some("private"),
+      None => None,
+    };
+    let is_virtual = self.is_virtual.then_some("virtual");
+    write!(
+      f,
+      "{}",
+      access
+        .into_iter()
+        .chain(is_virtual)
+        .collect::<Vec<_>>()
+        .join(" ")
+    )
+  }
 }
 
 impl Graph {
@@ -33,10 +71,67 @@ impl Graph {
     }
   }
 
+  pub fn add_edge_with_attributes(
+    &mut self,
+    u: &node::Node,
+    v: &node::Node,
+    attributes: &EdgeAttributes,
+  ) {
+    self.add_edge(u, v);
+    if *attributes != EdgeAttributes::default() {
+      self
+        .attributes
+        .insert((u.name.clone(), v.name.clone()), attributes.clone());
+    }
+  }
+
+  pub fn get_attributes(&self, u: &node::Node, v: &node::Node) -> Option<&EdgeAttributes> {
+    self.attributes.get(&(u.name.clone(), v.name.clone()))
+  }
+
   pub fn get_adjacencies(&self, u: &node::Node) -> Option<&Vec<node::Node>> {
     self.edges.get(u)
   }
 
+  // Merging partial graphs in order yields the same graph as extracting everything into one
+  pub fn merge(&mut self, other: &Graph) {
+    for u in other.nodes.values() {
+      self.add_node(u);
+    }
+    for (u, out_edges) in other.edges.iter() {
+      self
+        .edges
+        .entry(u.clone())
+        .or_default()
+        .extend_from_slice(out_edges);
+    }
+    self.attributes.extend(other.attributes.clone());
+  }
+
+  // Rewrite the locations of every node, which must keep distinct nodes apart
+  pub fn map_locations(self, f: impl Fn(&mut node::Location)) -> Self {
+    let map = |mut u: node::Node| {
+      u.location
+        .iter_mut()
+        .filter(|loc| !loc.is_empty())
+        .for_each(&f);
+      u
+    };
+    Graph {
+      nodes: self
+        .nodes
+        .into_iter()
+        .map(|(name, u)| (name, map(u)))
+        .collect(),
+      edges: self
+        .edges
+        .into_iter()
+        .map(|(u, out_edges)| (map(u), out_edges.into_iter().map(map).collect()))
+        .collect(),
+      attributes: self.attributes,
+    }
+  }
+
   pub fn reverse(self) -> Self {
     let mut reverse_graph = Graph::new();
     reverse_graph.nodes = self.nodes;
@@ -45,6 +140,11 @@ impl Graph {
         reverse_graph.add_edge(&v, &u);
       }
     }
+    reverse_graph.attributes = self
+      .attributes
+      .into_iter()
+      .map(|((u, v), attributes)| ((v, u), attributes))
+      .collect();
     reverse_graph
   }
 }
@@ -0,0 +1,233 @@
+use crate::analyzer;
+use crate::graph;
+use crate::node;
+use crate::syntaxtree;
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+use super::Scope;
+
+type Call = node::Node;
+
+pub struct CallAnalyzer {
+  method_pool: OnceLock<HashMap<String, Call>>,
+  class_pool: OnceLock<HashMap<String, Option<String>>>,
+}
+
+// The calls made by the methods of a file, in the order they are found
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Calls {
+  calls: Vec<(String, Callee)>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+enum Callee {
+  // e.g., `f()` or `this.f()`, by the methods of the classes around the call, innermost first, or
+  // the method it imports statically
+  Member(Vec<String>, String),
+  // e.g., `Util.f()` or `new Util()`, by the classes it may be called on, along with how it is
+  // written
+  Static(Vec<String>, String, String),
+  // e.g., `x.f()` or `super.f()`, on an object of an unknown class
+  Method(String),
+}
+
+impl CallAnalyzer {
+  pub fn new() -> Self {
+    CallAnalyzer {
+      method_pool: OnceLock::new(),
+      class_pool: OnceLock::new(),
+    }
+  }
+}
+
+// Objects named in upper camel case are classes, whose static methods are called (e.g.,
+// `Collections.sort()` or `java.util.Collections.sort()`)
+fn callee(
+  syntax_tree: &syntaxtree::SyntaxTree,
+  node: &tree_sitter::Node,
+  scopes: &[(usize, Scope)],
+  imports: &super::Imports,
+) -> Option<Callee> {
+  // Constructors are named after their class, e.g., `Outer.Inner.Inner()`
+  if node.kind() == "object_creation_expression" {
+    let class = super::type_name(syntax_tree, &node.child_by_field_name("type")?);
+    let name = class.rsplit('.').next().unwrap_or(&class);
+    return Some(Callee::Static(
+      imports.resolve(&class, scopes),
+      format!("{}()", name),
+      format!("new {}()", class),
+    ));
+  }
+  let name = String::from(syntax_tree.source(&node.child_by_field_name("name")?));
+  let object = match node.child_by_field_name("object") {
+    Some(object) => object,
+    None => {
+      let methods = scopes
+        .iter()
+        .rev()
+        .filter_map(|(_, scope)| match scope {
+          Scope::Class(class) => Some(class),
+          _ => None,
+        })
+        .chain(imports.statics.get(&name))
+        .map(|class| format!("{}.{}()", class, name))
+        .collect();
+      return Some(Callee::Member(methods, name));
+    }
+  };
+  match object.kind() {
+    "this" => Some(Callee::Member(
+      super::class(scopes)
+        .map(|class| format!("{}.{}()", class, name))
+        .into_iter()
+        .collect(),
+      name,
+    )),
+    "identifier" | "field_access" => {
+      let object = syntax_tree.source(&object);
+      let is_class = |segment: &str| segment.starts_with(|c: char| c.is_ascii_uppercase());
+      if !object.rsplit('.').next().is_some_and(is_class) {
+        return Some(Callee::Method(name));
+      }
+      // A class qualified by its package is looked up as written
+      let classes = match object.split('.').next().is_some_and(is_class) {
+        true => imports.resolve(object, scopes),
+        false => vec![String::from(object)],
+      };
+      Some(Callee::Static(
+        classes,
+        format!("{}()", name),
+        format!("{}.{}()", object, name),
+      ))
+    }
+    _ => Some(Callee::Method(name)),
+  }
+}
+
+impl analyzer::Analyzer for CallAnalyzer {
+  type Summary = Calls;
+
+  fn name(&self) -> &'static str {
+    "call"
+  }
+
+  fn extract_nodes(&self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph) {
+    let package = super::package(syntax_tree);
+    super::walk(syntax_tree, |node, scopes| {
+      if !matches!(
+        node.kind(),
+        "method_declaration" | "constructor_declaration"
+      ) {
+        return;
+      }
+      if let Some(name) = node.child_by_field_name("name") {
+        graph.add_node(&super::new_node(
+          &format!("{}()", super::qualify(syntax_tree.source(&name), scopes)),
+          syntax_tree,
+          name.start_position().row + 1,
+          package.as_deref(),
+        ));
+      }
+    });
+  }
+
+  fn summarize(&self, syntax_tree: &syntaxtree::SyntaxTree) -> Calls {
+    let imports = super::Imports::new(syntax_tree);
+    let mut calls = vec![];
+    super::walk(syntax_tree, |node, scopes| {
+      if !matches!(
+        node.kind(),
+        "method_invocation" | "object_creation_expression"
+      ) {
+        return;
+      }
+      let caller = scopes.iter().rev().find_map(|(_, scope)| match scope {
+        Scope::Method(method) => Some(method),
+        _ => None,
+      });
+      if let (Some(caller), Some(callee)) = (caller, callee(syntax_tree, node, scopes, &imports)) {
+        calls.push((caller.clone(), callee));
+      }
+    });
+    Calls { calls }
+  }
+
+  // Classes not found through the imports of a file are looked up by their simple name (e.g.,
+  // `Util.f()` for `com.acme.Util.f()`), if no other class has it
+  fn extract_edges(&self, calls: &Calls, graph: &graph::Graph, partial_graph: &mut graph::Graph) {
+    let method_pool = self.method_pool.get_or_init(|| {
+      let mut method_pool = HashMap::<String, Call>::new();
+      for (qualified_name, call) in graph.nodes.iter() {
+        if let Some(index) = qualified_name.rfind('.') {
+          method_pool
+            .entry(String::from(&qualified_name[index + 1..]))
+            .and_modify(|method| method.merge_node(call))
+            .or_insert_with(|| call.clone());
+        }
+      }
+      method_pool
+    });
+    let class_pool = self.class_pool.get_or_init(|| {
+      let classes = graph
+        .nodes
+        .keys()
+        .filter_map(|qualified_name| Some(qualified_name.rsplit_once('.')?.0))
+        .collect::<HashSet<_>>();
+      let mut class_pool = HashMap::<String, Option<String>>::new();
+      for class in classes {
+        class_pool
+          .entry(String::from(analyzer::unqualified(class, ".")))
+          .and_modify(|unique| *unique = None)
+          .or_insert_with(|| Some(String::from(class)));
+      }
+      class_pool
+    });
+
+    for (caller, callee) in calls.calls.iter() {
+      let call = match graph.get_node(caller) {
+        Some(call) => call,
+        None => continue,
+      };
+      // Methods not found in the classes around a call may be inherited
+      let (callee, name) = match callee {
+        Callee::Member(methods, name) => {
+          let method = format!("{}()", name);
+          let callee = methods
+            .iter()
+            .find_map(|method| graph.get_node(method))
+            .or_else(|| method_pool.get(&method));
+          (callee, method)
+        }
+        Callee::Static(classes, method, written) => {
+          let callee = classes
+            .iter()
+            .find_map(|class| graph.get_node(&format!("{}.{}", class, method)))
+            .or_else(|| {
+              classes.iter().find_map(|class| {
+                let class = class_pool
+                  .get(analyzer::unqualified(class, "."))?
+                  .as_ref()?;
+                graph.get_node(&format!("{}.{}", class, method))
+              })
+            });
+          (callee, written.clone())
+        }
+        Callee::Method(name) => {
+          let method = format!("{}()", name);
+          (method_pool.get(&method), method)
+        }
+      };
+      match callee {
+        Some(callee) => partial_graph.add_edge(call, callee),
+        None => {
+          let callee = Call::new_without_loc(&name);
+          partial_graph.add_node(&callee);
+          partial_graph.add_edge(call, &callee);
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,125 @@
+use crate::analyzer;
+use crate::graph;
+use crate::node;
+use crate::syntaxtree;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+type Class = node::Node;
+
+pub struct ClassAnalyzer {
+  class_pool: OnceLock<HashMap<String, Option<Class>>>,
+}
+
+// A class along with its base classes, each by the classes it may stand for and how it is written
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Inheritance {
+  class: String,
+  baseclasses: Vec<(Vec<String>, String)>,
+}
+
+impl ClassAnalyzer {
+  pub fn new() -> Self {
+    ClassAnalyzer {
+      class_pool: OnceLock::new(),
+    }
+  }
+}
+
+impl analyzer::Analyzer for ClassAnalyzer {
+  type Summary = Vec<Inheritance>;
+
+  fn name(&self) -> &'static str {
+    "class"
+  }
+
+  fn extract_nodes(&self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph) {
+    let package = super::package(syntax_tree);
+    super::walk(syntax_tree, |node, scopes| {
+      if !super::is_class(node) {
+        return;
+      }
+      if let Some(name) = node.child_by_field_name("name") {
+        graph.add_node(&super::new_node(
+          &super::qualify(syntax_tree.source(&name), scopes),
+          syntax_tree,
+          name.start_position().row + 1,
+          package.as_deref(),
+        ));
+      }
+    });
+  }
+
+  // Both `extends` and `implements` make base classes
+  fn summarize(&self, syntax_tree: &syntaxtree::SyntaxTree) -> Vec<Inheritance> {
+    let imports = super::Imports::new(syntax_tree);
+    let mut inheritances = Vec::<Inheritance>::new();
+    super::walk(syntax_tree, |node, scopes| {
+      if !super::is_class(node) {
+        return;
+      }
+      let class = match node.child_by_field_name("name") {
+        Some(name) => super::qualify(syntax_tree.source(&name), scopes),
+        None => return,
+      };
+      let baseclass = |ty: &tree_sitter::Node| {
+        let name = super::type_name(syntax_tree, ty);
+        (imports.resolve(&name, scopes), name)
+      };
+      let mut baseclasses = vec![];
+      let mut cursor = node.walk();
+      for clause in node.named_children(&mut cursor) {
+        if !matches!(
+          clause.kind(),
+          "superclass" | "super_interfaces" | "extends_interfaces"
+        ) {
+          continue;
+        }
+        let mut cursor = clause.walk();
+        for ty in clause.named_children(&mut cursor) {
+          if ty.kind() == "type_list" {
+            let mut cursor = ty.walk();
+            baseclasses.extend(ty.named_children(&mut cursor).map(|ty| baseclass(&ty)));
+          } else {
+            baseclasses.push(baseclass(&ty));
+          }
+        }
+      }
+      if !baseclasses.is_empty() {
+        inheritances.push(Inheritance { class, baseclasses });
+      }
+    });
+    inheritances
+  }
+
+  // Classes not found through the imports of a file are looked up by their simple name (e.g.,
+  // `Base` for `com.acme.Base`), if no other class has it
+  fn extract_edges(
+    &self,
+    inheritances: &Vec<Inheritance>,
+    graph: &graph::Graph,
+    partial_graph: &mut graph::Graph,
+  ) {
+    let class_pool = self
+      .class_pool
+      .get_or_init(|| analyzer::unqualified_pool(graph, "."));
+    for inheritance in inheritances {
+      let class = match graph.get_node(&inheritance.class) {
+        Some(class) => class,
+        None => continue,
+      };
+      for (candidates, written) in inheritance.baseclasses.iter() {
+        match analyzer::lookup(candidates, graph, class_pool, ".") {
+          Some(baseclass) => partial_graph.add_edge(baseclass, class),
+          None => {
+            let baseclass = Class::new_without_loc(written);
+            partial_graph.add_node(&baseclass);
+            partial_graph.add_edge(&baseclass, class);
+          }
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,233 @@
+pub mod call;
+pub mod class;
+
+use crate::analyzer;
+use crate::language::{self, Grammar};
+use crate::node;
+use crate::syntaxtree;
+
+use std::collections::HashMap;
+
+pub struct Java;
+
+impl language::Language for Java {
+  fn grammars(&self) -> &'static [(&'static str, Grammar)] {
+    &[("java", Grammar::Java)]
+  }
+
+  fn extensions(&self) -> &'static [(&'static str, Grammar)] {
+    &[("java", Grammar::Java)]
+  }
+
+  fn tree_sitter(&self, _grammar: Grammar) -> tree_sitter::Language {
+    tree_sitter_java::language()
+  }
+
+  fn analyzer(&self, kind: analyzer::Kind) -> Option<Box<dyn analyzer::AnyAnalyzer>> {
+    match kind {
+      analyzer::Kind::Call => Some(Box::new(call::CallAnalyzer::new())),
+      analyzer::Kind::Class => Some(Box::new(class::ClassAnalyzer::new())),
+      analyzer::Kind::Include => None,
+    }
+  }
+}
+
+// The package, classes and methods enclosing a node by their qualified name, each along with the
+// byte where it ends
+enum Scope {
+  Package(String),
+  Class(String),
+  Method(String),
+}
+
+// Walk the declarations of a file, calling `visit` with the scopes around each node, the outermost
+// being the package of the file, if any. Classes are qualified by their package or their enclosing
+// class (e.g., `com.acme.Outer.Inner`), and methods by their class (e.g., `com.acme.Outer.run()`).
+fn walk<'a>(
+  syntax_tree: &'a syntaxtree::SyntaxTree,
+  mut visit: impl FnMut(&tree_sitter::Node<'a>, &[(usize, Scope)]),
+) {
+  let mut scopes = package(syntax_tree)
+    .map(|package| (usize::MAX, Scope::Package(package)))
+    .into_iter()
+    .collect::<Vec<_>>();
+  for node in syntax_tree.iter() {
+    while scopes
+      .last()
+      .is_some_and(|(end, _)| node.start_byte() >= *end)
+    {
+      scopes.pop();
+    }
+    visit(&node, &scopes);
+
+    let scope = match (is_class(&node), node.kind()) {
+      (true, _) => node
+        .child_by_field_name("name")
+        .map(|name| Scope::Class(qualify(syntax_tree.source(&name), &scopes))),
+      (false, "method_declaration" | "constructor_declaration") => node
+        .child_by_field_name("name")
+        .map(|name| Scope::Method(format!("{}()", qualify(syntax_tree.source(&name), &scopes)))),
+      _ => None,
+    };
+    if let Some(scope) = scope {
+      scopes.push((node.end_byte(), scope));
+    }
+  }
+}
+
+fn is_class(node: &tree_sitter::Node) -> bool {
+  matches!(
+    node.kind(),
+    "class_declaration"
+      | "interface_declaration"
+      | "enum_declaration"
+      | "record_declaration"
+      | "annotation_type_declaration"
+  )
+}
+
+// Qualify a name by the innermost class or package around it
+fn qualify(name: &str, scopes: &[(usize, Scope)]) -> String {
+  match scopes.iter().rev().find_map(|(_, scope)| match scope {
+    Scope::Package(prefix) | Scope::Class(prefix) => Some(prefix),
+    _ => None,
+  }) {
+    Some(prefix) => format!("{}.{}", prefix, name),
+    None => String::from(name),
+  }
+}
+
+fn class(scopes: &[(usize, Scope)]) -> Option<&String> {
+  scopes.iter().rev().find_map(|(_, scope)| match scope {
+    Scope::Class(class) => Some(class),
+    _ => None,
+  })
+}
+
+// The classes a file refers to by a simple name (e.g., `Util` for `com.acme.util.Util`), apart
+// from those of its own package
+struct Imports {
+  package: Option<String>,
+  // From a class or a statically imported method to the class it names or belongs to
+  classes: HashMap<String, String>,
+  statics: HashMap<String, String>,
+  // Packages imported with `*`
+  packages: Vec<String>,
+}
+
+impl Imports {
+  fn new(syntax_tree: &syntaxtree::SyntaxTree) -> Self {
+    let mut imports = Imports {
+      package: package(syntax_tree),
+      classes: HashMap::new(),
+      statics: HashMap::new(),
+      packages: vec![],
+    };
+    let root = match syntax_tree.iter().next() {
+      Some(root) => root,
+      None => return imports,
+    };
+    let mut cursor = root.walk();
+    for import in root
+      .named_children(&mut cursor)
+      .filter(|node| node.kind() == "import_declaration")
+    {
+      let mut cursor = import.walk();
+      let children = import.children(&mut cursor).collect::<Vec<_>>();
+      let is_static = children.iter().any(|child| child.kind() == "static");
+      let is_wildcard = children.iter().any(|child| child.kind() == "asterisk");
+      let name = match children
+        .iter()
+        .find(|child| matches!(child.kind(), "identifier" | "scoped_identifier"))
+      {
+        Some(name) => String::from(syntax_tree.source(name)),
+        None => continue,
+      };
+      match (is_static, is_wildcard, name.rsplit_once('.')) {
+        (false, true, _) => imports.packages.push(name),
+        (false, false, Some((_, simple_name))) => {
+          imports
+            .classes
+            .insert(String::from(simple_name), name.clone());
+        }
+        (true, false, Some((class, member))) => {
+          imports
+            .statics
+            .insert(String::from(member), String::from(class));
+        }
+        _ => (),
+      }
+    }
+    imports
+  }
+
+  // The classes a name written among `scopes` may stand for, most likely first: a member class of
+  // the classes around it, an imported class, a class of the same package or of a package imported
+  // with `*`, then the name as written (e.g., `java.util.List`)
+  fn resolve(&self, name: &str, scopes: &[(usize, Scope)]) -> Vec<String> {
+    let (first, rest) = match name.split_once('.') {
+      Some((first, rest)) => (first, Some(rest)),
+      None => (name, None),
+    };
+    let mut candidates = scopes
+      .iter()
+      .rev()
+      .filter_map(|(_, scope)| match scope {
+        Scope::Class(class) => Some(format!("{}.{}", class, name)),
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    if let Some(class) = self.classes.get(first) {
+      candidates.push(match rest {
+        Some(rest) => format!("{}.{}", class, rest),
+        None => class.clone(),
+      });
+    }
+    candidates.extend(
+      self
+        .package
+        .iter()
+        .chain(self.packages.iter())
+        .map(|package| format!("{}.{}", package, name)),
+    );
+    candidates.push(String::from(name));
+    candidates
+  }
+}
+
+// The package declared by a file, e.g., `com.example.util`
+fn package(syntax_tree: &syntaxtree::SyntaxTree) -> Option<String> {
+  let root = syntax_tree.iter().next()?;
+  let mut cursor = root.walk();
+  let package = root
+    .named_children(&mut cursor)
+    .find(|node| node.kind() == "package_declaration")?;
+  let mut cursor = package.walk();
+  let name = package
+    .named_children(&mut cursor)
+    .find(|name| matches!(name.kind(), "identifier" | "scoped_identifier"))?;
+  Some(String::from(syntax_tree.source(&name)))
+}
+
+// Nodes are shown by package, if the file declares one
+fn new_node(
+  name: &str,
+  syntax_tree: &syntaxtree::SyntaxTree,
+  row: usize,
+  package: Option<&str>,
+) -> node::Node {
+  match package {
+    Some(package) => node::Node::new_in_package(name, &syntax_tree.file, row, package),
+    None => node::Node::new(name, &syntax_tree.file, row),
+  }
+}
+
+// The name of a type without its type arguments, e.g., `List` for `List<T>`
+fn type_name(syntax_tree: &syntaxtree::SyntaxTree, node: &tree_sitter::Node) -> String {
+  let source = syntax_tree.source(node);
+  let name = match source.find('<') {
+    Some(index) => &source[..index],
+    None => source,
+  };
+  name.chars().filter(|c| !c.is_whitespace()).collect()
+}
@@ -0,0 +1,221 @@
+use crate::analyzer;
+use crate::cpp;
+use crate::go;
+use crate::java;
+use crate::python;
+use crate::rust;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::path;
+use std::sync::OnceLock;
+
+// Every grammar of every language
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+pub enum Grammar {
+  C,
+  Cpp,
+  Rust,
+  Python,
+  Go,
+  Java,
+}
+
+// What a language module (e.g., `cpp`) provides
+pub trait Language: Sync {
+  // Its grammars, by the names given to --ext (e.g., `cu=cpp`)
+  fn grammars(&self) -> &'static [(&'static str, Grammar)];
+
+  // The file extensions parsed by its grammars unless configured otherwise
+  fn extensions(&self) -> &'static [(&'static str, Grammar)];
+
+  fn tree_sitter(&self, grammar: Grammar) -> tree_sitter::Language;
+
+  // Whether sources go through the C preprocessor (#if, --ignore-macro and --expand-macros)
+  fn preprocessed(&self) -> bool {
+    false
+  }
+
+  // None if the language has nothing to show for that kind of graph
+  fn analyzer(&self, kind: analyzer::Kind) -> Option<Box<dyn analyzer::AnyAnalyzer>>;
+}
+
+pub const LANGUAGES: &[&dyn Language] = &[
+  &cpp::Cpp,
+  &rust::Rust,
+  &python::Python,
+  &go::Go,
+  &java::Java,
+];
+
+impl Grammar {
+  pub fn from_name(name: &str) -> Result<Self> {
+    let name = name.to_ascii_lowercase();
+    LANGUAGES
+      .iter()
+      .flat_map(|language| language.grammars())
+      .find(|(grammar_name, _)| *grammar_name == name)
+      .map(|(_, grammar)| *grammar)
+      .with_context(|| format!("Unknown grammar: \"{}\"!", name))
+  }
+
+  // The index of the language of the grammar in `LANGUAGES`
+  pub fn language(&self) -> usize {
+    LANGUAGES
+      .iter()
+      .position(|language| {
+        language
+          .grammars()
+          .iter()
+          .any(|(_, grammar)| grammar == self)
+      })
+      .unwrap()
+  }
+
+  pub fn tree_sitter(&self) -> tree_sitter::Language {
+    LANGUAGES[self.language()].tree_sitter(*self)
+  }
+
+  pub fn preprocessed(&self) -> bool {
+    LANGUAGES[self.language()].preprocessed()
+  }
+}
+
+// Decide which files are sources, and which grammar parses them
+pub struct Extensions {
+  grammars: HashMap<String, Grammar>,
+  // Extensions whose grammar is given by .ast-flow.toml or --ext
+  explicit: HashSet<String>,
+}
+
+impl Extensions {
+  // Both `config` (from .ast-flow.toml) and `extensions` (from --ext) map extensions to grammar
+  // names. The command line takes precedence.
+  pub fn new(config: &HashMap<String, String>, extensions: &[String]) -> Result<Self> {
+    let mut grammars = LANGUAGES
+      .iter()
+      .flat_map(|language| language.extensions())
+      .map(|(extension, grammar)| (String::from(*extension), *grammar))
+      .collect::<HashMap<_, _>>();
+    let mut explicit = HashSet::new();
+    let extensions = extensions
+      .iter()
+      .map(|extension| extension.split_once('=').unwrap_or((extension, "cpp")));
+    for (extension, grammar) in config
+      .iter()
+      .map(|(extension, grammar)| (extension.as_str(), grammar.as_str()))
+      .chain(extensions)
+    {
+      let extension = String::from(extension.trim_start_matches('.'));
+      grammars.insert(extension.clone(), Grammar::from_name(grammar)?);
+      explicit.insert(extension);
+    }
+    Ok(Extensions { grammars, explicit })
+  }
+
+  // Whether `.h` files may be parsed as C rather than C++, i.e., unless their grammar is given,
+  // when there are C sources but no C++ ones among `files`. Those using C++ (see `uses_cpp`) are
+  // still parsed as C++.
+  pub fn c_headers<'a>(
+    &self,
+    files: impl Iterator<Item = (&'a path::Path, Grammar)> + Clone,
+  ) -> bool {
+    let sources = files.filter(|(file, _)| file.extension().is_none_or(|ext| ext != "h"));
+    !self.explicit.contains("h")
+      && sources.clone().any(|(_, grammar)| grammar == Grammar::C)
+      && sources.clone().all(|(_, grammar)| grammar != Grammar::Cpp)
+  }
+
+  pub fn grammar(&self, path: &path::Path) -> Option<Grammar> {
+    self.grammar_with(path, || {
+      let mut head = vec![];
+      fs::File::open(path)
+        .ok()?
+        .take(4096)
+        .read_to_end(&mut head)
+        .ok()?;
+      Some(head)
+    })
+  }
+
+  // Like `grammar`, with `head` returning the beginning of the file if its content is needed
+  pub fn grammar_with(
+    &self,
+    path: &path::Path,
+    head: impl FnOnce() -> Option<Vec<u8>>,
+  ) -> Option<Grammar> {
+    match path.extension() {
+      Some(extension) => self.grammars.get(extension.to_str()?).copied(),
+      None => detect(&head()?),
+    }
+  }
+}
+
+// Recognize extensionless headers (e.g., <vector>) by a modeline or the first directive, and
+// Python scripts by their shebang
+fn detect(head: &[u8]) -> Option<Grammar> {
+  static SHEBANG: OnceLock<Regex> = OnceLock::new();
+  static MODELINE: OnceLock<Regex> = OnceLock::new();
+  static DIRECTIVE: OnceLock<Regex> = OnceLock::new();
+  let modeline = MODELINE.get_or_init(|| {
+    Regex::new(
+      r"(?i)-\*-\s*(?:mode:\s*)?(c|c\+\+)\s*;?\s*-\*-|\bvim?:.*\b(?:ft|filetype)=(c|cpp)\b",
+    )
+    .unwrap()
+  });
+  let directive =
+    DIRECTIVE.get_or_init(|| Regex::new(r"^\s*#\s*(?:pragma\s+once|ifndef|include)\b").unwrap());
+
+  let shebang = SHEBANG.get_or_init(|| Regex::new(r"^#!.*\bpython[0-9.]*\b").unwrap());
+
+  let head = String::from_utf8_lossy(head);
+  if head
+    .lines()
+    .next()
+    .is_some_and(|line| shebang.is_match(line))
+  {
+    return Some(Grammar::Python);
+  }
+  if let Some(captures) = head
+    .lines()
+    .take(5)
+    .find_map(|line| modeline.captures(line))
+  {
+    return match captures
+      .get(1)
+      .or(captures.get(2))
+      .map(|name| name.as_str())
+    {
+      Some("c" | "C") => Some(Grammar::C),
+      _ => Some(Grammar::Cpp),
+    };
+  }
+  head
+    .lines()
+    .map(str::trim)
+    .find(|line| {
+      !line.is_empty()
+        && !line.starts_with("//")
+        && !line.starts_with("/*")
+        && !line.starts_with('*')
+    })
+    .filter(|line| directive.is_match(line))
+    .map(|_| Grammar::Cpp)
+}
+
+// Whether a header uses C++, e.g., a header-only library among C sources
+pub fn uses_cpp(source: &str) -> bool {
+  static CPP: OnceLock<Regex> = OnceLock::new();
+  CPP
+    .get_or_init(|| {
+      Regex::new(
+        r#"(?m)^\s*(?:template\s*<|(?:inline\s+)?namespace(?:\s+[\w:]+)?\s*\{|using\s+namespace\b|class\s+\w+[^;]*?[:{]|extern\s+"C\+\+")"#,
+      )
+      .unwrap()
+    })
+    .is_match(source)
+}
@@ -1,41 +1,87 @@
 mod analyzer;
+mod cache;
 mod cli;
 mod color;
+mod compdb;
+mod config;
 mod cpp;
 mod display;
 mod driver;
+mod git;
+mod go;
 mod graph;
+mod java;
+mod language;
 mod node;
 mod pathwalk;
+mod preprocessor;
+mod python;
+mod rust;
 mod syntaxtree;
 
-use clap::Parser;
-use cpp::call::CallAnalyzer;
-use cpp::class::ClassAnalyzer;
-use std::io::{self, Write};
+use clap::{CommandFactory, FromArgMatches};
+use std::io::{self, IsTerminal, Write};
+use std::process;
 
-fn main() {
-  let mut pager =
-    pager::Pager::with_pager("less --raw-control-chars --ignore-case --quit-if-one-screen");
-  pager.setup();
+fn execute(kind: analyzer::Kind, mut arg: cli::Arg) {
+  let new_analyzer = || analyzer::Registry::new(kind);
+  // The output keeps changing in watch mode, so it can't go through the pager
+  if arg.watch {
+    if !io::stdout().is_terminal() {
+      arg.color = false;
+    }
+    if let Err(e) = driver::Driver::watch(new_analyzer, &arg) {
+      eprintln!("[Error] {}", e);
+    }
+    return;
+  }
+
+  // The pager takes over the exit status, which --strict has to report
+  if arg.strict.is_none() {
+    let mut pager =
+      pager::Pager::with_pager("less --raw-control-chars --ignore-case --quit-if-one-screen");
+    pager.setup();
+    if arg.color && !pager.is_on() {
+      arg.color = false;
+    }
+  } else if !io::stdout().is_terminal() {
+    arg.color = false;
+  }
 
-  let mut cli = cli::Cli::parse();
-  match &mut cli.command {
-    cli::Command::Class(arg) | cli::Command::Call(arg) => {
-      if arg.color && !pager.is_on() {
-        arg.color = false;
+  match driver::Driver::run(&new_analyzer(), &arg) {
+    Err(e) => {
+      eprintln!("[Error] {}", e);
+      process::exit(1);
+    }
+    Ok(output) => {
+      if let Err(e) = write!(io::stdout(), "{}", output) {
+        eprintln!("[Warning] {}", e);
       }
     }
   }
+}
 
-  if let Err(e) = write!(
-    io::stdout(),
-    "{}",
-    match cli.command {
-      cli::Command::Class(arg) => driver::Driver::run(&mut ClassAnalyzer::new(), &arg),
-      cli::Command::Call(arg) => driver::Driver::run(&mut CallAnalyzer::new(), &arg),
+fn main() {
+  let command = cli::Cli::command();
+  let matches = command.clone().get_matches();
+  let cli = cli::Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+  // Options missing from the command line are taken from .ast-flow.toml
+  let (name, sub_matches) = matches.subcommand().unwrap();
+  let sub_command = command.find_subcommand(name).unwrap();
+  let configure = |arg: cli::Arg| match config::Config::load(&arg.paths[0].path)
+    .and_then(|config| config.apply(arg, sub_command, sub_matches))
+  {
+    Err(e) => {
+      eprintln!("[Error] {}", e);
+      process::exit(1);
     }
-  ) {
-    eprintln!("[Warning] {}", e);
+    Ok(arg) => arg,
+  };
+
+  match cli.command {
+    cli::Command::Class(arg) => execute(analyzer::Kind::Class, configure(arg)),
+    cli::Command::Call(arg) => execute(analyzer::Kind::Call, configure(arg)),
+    cli::Command::Include(arg) => execute(analyzer::Kind::Include, configure(arg)),
   }
 }
@@ -1,26 +1,44 @@
+use serde::{Deserialize, Serialize};
 use std::hash::Hash;
 
-#[derive(Clone, Default, Hash, Eq, PartialEq)]
+#[derive(Clone, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Node {
   pub name: String,
   pub alias: bool,
   pub location: Vec<Location>,
 }
 
-#[derive(Clone, Default, Hash, Eq, PartialEq)]
+#[derive(Clone, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Location {
+  // The label of the `--path` the file was found under, if it was given one
+  pub label: Option<String>,
   pub file: String,
+  // The package of the file in languages having them (e.g., `com.example.util` in Java), whose
+  // nodes are shown together rather than by file
+  pub package: Option<String>,
+  // The index of the language of the file in `language::LANGUAGES`, so that a mixed-language
+  // project doesn't link a call or a base class to a node of another language
+  pub language: Option<usize>,
   row: usize,
 }
 
 impl Location {
   pub fn new(file: String, row: usize) -> Location {
-    Location { file, row }
+    Location {
+      label: None,
+      file,
+      package: None,
+      language: None,
+      row,
+    }
   }
 
   pub fn new_empty() -> Location {
     Location {
+      label: None,
       file: String::new(),
+      package: None,
+      language: None,
       row: 0,
     }
   }
@@ -32,7 +50,10 @@ impl Location {
 
 impl std::fmt::Display for Location {
   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-    write!(f, "[{} +{}]", self.file, self.row)
+    match self.label {
+      Some(ref label) => write!(f, "[{}:{} +{}]", label, self.file, self.row),
+      None => write!(f, "[{} +{}]", self.file, self.row),
+    }
   }
 }
 
@@ -53,6 +74,16 @@ impl Node {
     }
   }
 
+  pub fn new_in_package(name: &str, file: &str, row: usize, package: &str) -> Self {
+    let mut location = Location::new(String::from(file), row);
+    location.package = Some(String::from(package));
+    Node {
+      name: String::from(name),
+      alias: false,
+      location: vec![location],
+    }
+  }
+
   pub fn new_without_loc(name: &str) -> Self {
     Node {
       name: String::from(name),
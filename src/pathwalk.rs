@@ -1,37 +1,113 @@
+use crate::language;
+
 use anyhow::{Context, Result};
 use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::iter;
 use std::path;
 
-pub struct FileIterator {
+// Matched against directory names rather than paths, so that e.g. `latest/` is not taken for a test
+const DEFAULT_EXCLUDE_DIRS: &[&str] = &[
+  "benchmark*",
+  "build",
+  "build[-_]*",
+  "*[-_]build",
+  "cmake-build-*",
+  "contrib",
+  "example*",
+  "test",
+  "tests",
+  "test[-_]*",
+  "*[-_]test",
+  "*[-_]tests",
+  "testing",
+  "unittest*",
+  "thirdparty",
+  "third[-_]party",
+  "deps",
+  "*[-_]deps",
+];
+
+// Ignore files read in every directory, later ones taking precedence
+pub const IGNORE_FILES: &[&str] = &[
+  ".git/info/exclude",
+  ".gitignore",
+  ".ignore",
+  ".astflowignore",
+];
+
+pub struct FileIterator<'a> {
   root: path::PathBuf,
+  canonical_root: path::PathBuf,
   visited: bool,
-  stack: Vec<fs::ReadDir>,
+  // Every directory being read, along with the ignore rules it defines
+  stack: Vec<(fs::ReadDir, Option<Gitignore>)>,
+  // The ignore rules of the directories above `root` up to the repository root, deepest first
+  ancestor_ignores: Vec<Gitignore>,
   exclude_globset: GlobSet,
+  exclude_dirs: GlobSet,
+  no_ignore: bool,
+  extensions: &'a language::Extensions,
+  follow_symlinks: bool,
+  // Directories already walked, so that a symlink to an ancestor doesn't loop forever
+  visited_dirs: HashSet<DirId>,
+  // Files already returned, so that a symlinked tree isn't analyzed twice
+  visited_files: HashSet<path::PathBuf>,
+  // Symlinks to follow once the directories being read are done, in order, along with the ignore
+  // rules of the directories they are in. Files reached both through a symlink and without one are
+  // thus always found by the latter path.
+  symlinks: BTreeMap<path::PathBuf, Vec<Gitignore>>,
+  // The ignore rules of the directories above the symlink being followed
+  outer_ignores: Vec<Gitignore>,
 }
 
-impl FileIterator {
-  pub fn new(root: &path::PathBuf, exclude_globset: &[String], no_exclude: bool) -> Result<Self> {
-    let mut exclude_builder = GlobSetBuilder::new();
-    let mut exclude_globset = exclude_globset.to_owned();
-    if !no_exclude {
-      exclude_globset.push("*benchmark*/*".to_string());
-      exclude_globset.push("*build*/*".to_string());
-      exclude_globset.push("*contrib*/*".to_string());
-      exclude_globset.push("*example*/*".to_string());
-      exclude_globset.push("*test*/*".to_string());
-      exclude_globset.push("*thirdparty*/*".to_string());
-      exclude_globset.push("*third[-_]party*/*".to_string());
-      exclude_globset.push("*deps*/*".to_string());
-    }
-    for pattern in exclude_globset {
-      match Glob::new(&pattern) {
-        Err(e) => eprintln!("[Warning] {}", e),
-        Ok(glob) => {
-          exclude_builder.add(glob);
-        }
-      };
+// Identifies a directory however it is reached
+#[cfg(unix)]
+type DirId = (u64, u64);
+#[cfg(not(unix))]
+type DirId = path::PathBuf;
+
+#[cfg(unix)]
+fn dir_id(dir: &path::Path) -> Option<DirId> {
+  use std::os::unix::fs::MetadataExt;
+  let metadata = fs::metadata(dir).ok()?;
+  Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_id(dir: &path::Path) -> Option<DirId> {
+  dir.canonicalize().ok()
+}
+
+impl<'a> FileIterator<'a> {
+  pub fn new(
+    root: &path::PathBuf,
+    exclude_globset: &[String],
+    no_exclude: bool,
+    no_ignore: bool,
+    extensions: &'a language::Extensions,
+    follow_symlinks: bool,
+  ) -> Result<Self> {
+    let canonical_root = root
+      .canonicalize()
+      .with_context(|| format!("Failed to read: \"{}\"!", root.display()))?;
+
+    let mut ancestor_ignores = vec![];
+    if !no_ignore {
+      if let Some(repository) = canonical_root
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+      {
+        ancestor_ignores = canonical_root
+          .ancestors()
+          .skip(1)
+          .take_while(|dir| dir.starts_with(repository))
+          .filter_map(read_ignore_files)
+          .collect();
+      }
     }
 
     Ok(FileIterator {
@@ -40,51 +116,220 @@ impl FileIterator {
       stack: if root.is_file() {
         vec![]
       } else {
-        vec![fs::read_dir(root).with_context(|| format!("Failed to read: \"{}\"!", root.display()))?]
+        vec![(
+          fs::read_dir(root).with_context(|| format!("Failed to read: \"{}\"!", root.display()))?,
+          (!no_ignore)
+            .then(|| read_ignore_files(&canonical_root))
+            .flatten(),
+        )]
       },
-      exclude_globset: exclude_builder
-        .build()
-        .context("Failed to build GlobSet!")?,
+      canonical_root,
+      ancestor_ignores,
+      exclude_globset: build_globset(exclude_globset)?,
+      exclude_dirs: build_exclude_dirs(no_exclude)?,
+      no_ignore,
+      extensions,
+      follow_symlinks,
+      visited_dirs: dir_id(root).into_iter().collect(),
+      visited_files: HashSet::new(),
+      symlinks: BTreeMap::new(),
+      outer_ignores: vec![],
     })
   }
+
+  // Whether a file or directory found in the directory being read is to be analyzed or walked
+  fn is_walked(&self, entry_path: &path::Path, is_file: bool, is_dir: bool) -> bool {
+    let relative_path = match entry_path.strip_prefix(&self.root) {
+      Ok(relative_path) => relative_path,
+      Err(_) => return false,
+    };
+    if is_file {
+      self.exclude_globset.matches(relative_path).is_empty()
+        && !self.is_ignored(relative_path, false)
+    } else {
+      is_dir
+        && !entry_path.file_name().is_some_and(is_hidden)
+        && !entry_path
+          .file_name()
+          .is_some_and(|name| self.exclude_dirs.is_match(name))
+        && !self.is_ignored(relative_path, true)
+    }
+  }
+
+  // The ignore rules of the directories being read, deepest first
+  fn ignores(&self) -> impl Iterator<Item = &Gitignore> {
+    self
+      .stack
+      .iter()
+      .rev()
+      .filter_map(|(_, ignore)| ignore.as_ref())
+      .chain(self.outer_ignores.iter())
+  }
+
+  // The rules of the deepest directory that mentions the path win
+  fn is_ignored(&self, relative_path: &path::Path, is_dir: bool) -> bool {
+    if self.no_ignore {
+      return false;
+    }
+    let path = self.canonical_root.join(relative_path);
+    self
+      .ignores()
+      .chain(self.ancestor_ignores.iter())
+      .map(|ignore| ignore.matched(&path, is_dir))
+      .find(|matched| !matched.is_none())
+      .is_some_and(|matched| matches!(matched, Match::Ignore(_)))
+  }
 }
 
-impl iter::Iterator for FileIterator {
-  type Item = path::PathBuf;
+fn read_ignore_files(dir: &path::Path) -> Option<Gitignore> {
+  let mut builder = GitignoreBuilder::new(dir);
+  let mut found = false;
+  for file in IGNORE_FILES {
+    let file = dir.join(file);
+    if file.is_file() {
+      found = true;
+      if let Some(e) = builder.add(file) {
+        eprintln!("[Warning] {}", e);
+      }
+    }
+  }
+  if !found {
+    return None;
+  }
+  match builder.build() {
+    Err(e) => {
+      eprintln!("[Warning] {}", e);
+      None
+    }
+    Ok(ignore) => Some(ignore),
+  }
+}
+
+// The ignore rules of `dir` from the content of its ignore files, in the order of `IGNORE_FILES`
+pub fn build_ignore(
+  dir: &path::Path,
+  ignore_files: &[(path::PathBuf, String)],
+) -> Option<Gitignore> {
+  if ignore_files.is_empty() {
+    return None;
+  }
+  let mut builder = GitignoreBuilder::new(dir);
+  for (file, content) in ignore_files {
+    for line in content.lines() {
+      if let Err(e) = builder.add_line(Some(file.clone()), line) {
+        eprintln!("[Warning] {}", e);
+      }
+    }
+  }
+  match builder.build() {
+    Err(e) => {
+      eprintln!("[Warning] {}", e);
+      None
+    }
+    Ok(ignore) => Some(ignore),
+  }
+}
+
+// Directories such as `.git/` or `.github/` are never walked
+pub fn is_hidden(dir: &std::ffi::OsStr) -> bool {
+  dir.to_string_lossy().starts_with('.')
+}
+
+// The directory names excluded by default, unless `no_exclude` is set
+pub fn build_exclude_dirs(no_exclude: bool) -> Result<GlobSet> {
+  if no_exclude {
+    return build_globset(&[]);
+  }
+  build_globset(
+    &DEFAULT_EXCLUDE_DIRS
+      .iter()
+      .map(|dir| dir.to_string())
+      .collect::<Vec<_>>(),
+  )
+}
+
+pub fn build_globset(patterns: &[String]) -> Result<GlobSet> {
+  let mut builder = GlobSetBuilder::new();
+  for pattern in patterns {
+    match Glob::new(pattern) {
+      Err(e) => eprintln!("[Warning] {}", e),
+      Ok(glob) => {
+        builder.add(glob);
+      }
+    };
+  }
+  builder.build().context("Failed to build GlobSet!")
+}
+
+impl<'a> iter::Iterator for FileIterator<'a> {
+  type Item = (path::PathBuf, language::Grammar);
 
   fn next(&mut self) -> Option<Self::Item> {
     if self.root.is_file() {
       if !self.visited {
         self.visited = true;
-        Some(self.root.clone())
+        let grammar = self.extensions.grammar(&self.root);
+        Some((self.root.clone(), grammar.unwrap_or(language::Grammar::Cpp)))
       } else {
         None
       }
     } else {
       loop {
-        if let Some(current_dir) = self.stack.last_mut() {
-          if let Some(Ok(entry)) = current_dir.next() {
-            let entry_path = entry.path();
-            if let Ok(relative_path) = entry_path.strip_prefix(&self.root) {
-              if entry_path.is_file() && self.exclude_globset.matches(relative_path).is_empty() {
-                if let Some(ext) = relative_path.extension() {
-                  if let Some(ext) = ext.to_str() {
-                    if matches!(ext, "c" | "cc" | "cpp" | "c++" | "h" | "hh" | "hpp" | "h++") {
-                      return Some(entry_path);
-                    }
+        let (entry_path, is_file, is_dir) = match self.stack.last_mut() {
+          Some((current_dir, _)) => match current_dir.next() {
+            Some(Ok(entry)) => {
+              let entry_path = entry.path();
+              let (is_file, is_dir) = match entry.file_type() {
+                // Symlinks are skipped unless followed, which is done last (see `symlinks`)
+                Ok(file_type) if file_type.is_symlink() => {
+                  let (is_file, is_dir) = (entry_path.is_file(), entry_path.is_dir());
+                  if self.follow_symlinks && self.is_walked(&entry_path, is_file, is_dir) {
+                    let ignores = self.ignores().cloned().collect();
+                    self.symlinks.insert(entry_path, ignores);
                   }
+                  continue;
                 }
-              } else if entry_path.is_dir() && !relative_path.starts_with(".") {
-                if let Ok(read_dir) = fs::read_dir(entry_path) {
-                  self.stack.push(read_dir);
-                }
+                Ok(file_type) => (file_type.is_file(), file_type.is_dir()),
+                Err(_) => continue,
+              };
+              if !self.is_walked(&entry_path, is_file, is_dir) {
+                continue;
               }
+              (entry_path, is_file, is_dir)
             }
-          } else {
-            self.stack.pop();
+            _ => {
+              self.stack.pop();
+              continue;
+            }
+          },
+          None => {
+            let (entry_path, ignores) = self.symlinks.pop_first()?;
+            self.outer_ignores = ignores;
+            let (is_file, is_dir) = (entry_path.is_file(), entry_path.is_dir());
+            (entry_path, is_file, is_dir)
+          }
+        };
+
+        if is_file {
+          if !self.follow_symlinks
+            || entry_path
+              .canonicalize()
+              .is_ok_and(|file| self.visited_files.insert(file))
+          {
+            if let Some(grammar) = self.extensions.grammar(&entry_path) {
+              return Some((entry_path, grammar));
+            }
+          }
+        } else if is_dir && dir_id(&entry_path).is_some_and(|id| self.visited_dirs.insert(id)) {
+          if let Ok(read_dir) = fs::read_dir(&entry_path) {
+            let ignore = match entry_path.strip_prefix(&self.root) {
+              Ok(relative_path) if !self.no_ignore => {
+                read_ignore_files(&self.canonical_root.join(relative_path))
+              }
+              _ => None,
+            };
+            self.stack.push((read_dir, ignore));
           }
-        } else {
-          return None;
         }
       }
     }
@@ -0,0 +1,634 @@
+use crate::compdb;
+
+use std::collections::HashMap;
+
+enum Definition {
+  Object(String),
+  Function,
+  Undefined,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum State {
+  Active,   // Known to be compiled
+  Unknown,  // Might be compiled, kept for tree-sitter
+  Inactive, // Known not to be compiled, blanked out
+}
+
+struct Group {
+  parent: State,
+  taken: bool,
+  unknown: bool,
+  directives: Vec<usize>,
+}
+
+enum Token {
+  Identifier(String),
+  Number(Option<i64>),
+  Punctuator(&'static str),
+}
+
+// Ordered so that longer punctuators are matched first
+const PUNCTUATORS: [&str; 26] = [
+  "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "!", "~", "*", "/", "%", "+", "-", "<", ">", "&",
+  "^", "|", "?", ":", "(", ")", ",", "#",
+];
+
+const MAX_DEPTH: usize = 32;
+
+// Blank out the branches of `#if` groups that are known not to be compiled.
+// A condition is only known when the macros it depends on are explicitly defined or undefined,
+// either by `-D`/`-U` flags or by `#define`/`#undef` in active regions of the file itself;
+// otherwise both branches are kept, which is what tree-sitter would see without this pass.
+// Every byte except line breaks is replaced with a space, so byte offsets and rows stay intact.
+pub fn evaluate(source: &str, flags: &compdb::CompileFlags) -> String {
+  let mut macros = HashMap::new();
+  for (name, value) in flags.defines.iter() {
+    macros.insert(name.clone(), Definition::Object(value.clone()));
+  }
+  for name in flags.undefines.iter() {
+    macros.insert(name.clone(), Definition::Undefined);
+  }
+
+  let lines = source.split_inclusive('\n').collect::<Vec<_>>();
+  let mut blank = vec![false; lines.len()];
+  let mut groups = Vec::<Group>::new();
+  let mut state = State::Active;
+  let mut in_comment = false;
+  let mut i = 0;
+  while i < lines.len() {
+    if in_comment || !lines[i].trim_start().starts_with('#') {
+      in_comment = skip_comments(lines[i], in_comment).1;
+      blank[i] = state == State::Inactive;
+      i += 1;
+      continue;
+    }
+
+    let start = i;
+    let mut directive = String::new();
+    let mut comment_open = false;
+    loop {
+      let line = lines[i].trim_end_matches(['\n', '\r']);
+      comment_open = skip_comments(line, comment_open).1;
+      match line.strip_suffix('\\') {
+        Some(line) if i + 1 < lines.len() => {
+          directive.push_str(line);
+          i += 1;
+        }
+        // A block comment continues the directive until it is closed
+        _ if comment_open && i + 1 < lines.len() => {
+          directive.push_str(line);
+          directive.push('\n');
+          i += 1;
+        }
+        _ => {
+          directive.push_str(line);
+          break;
+        }
+      }
+    }
+    let (directive, still_in_comment) = skip_comments(&directive, false);
+    in_comment = still_in_comment;
+    let directive = directive.trim_start()[1..].trim_start();
+    let name_len = directive
+      .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+      .unwrap_or(directive.len());
+    let (name, rest) = directive.split_at(name_len);
+    let rest = rest.trim();
+    let directive_lines = start..=i;
+    i += 1;
+
+    match name {
+      "if" | "ifdef" | "ifndef" => {
+        let mut group = Group {
+          parent: state,
+          taken: false,
+          unknown: false,
+          directives: directive_lines.collect(),
+        };
+        let value = if state == State::Inactive {
+          Some(false)
+        } else {
+          condition(name, rest, &macros)
+        };
+        state = enter(&mut group, value);
+        groups.push(group);
+      }
+      "elif" | "elifdef" | "elifndef" | "else" => {
+        if let Some(group) = groups.last_mut() {
+          group.directives.extend(directive_lines);
+          state = if group.taken || group.parent == State::Inactive {
+            State::Inactive
+          } else if name == "else" {
+            enter(group, Some(true))
+          } else {
+            enter(group, condition(&name[2..], rest, &macros))
+          };
+        }
+      }
+      "endif" => {
+        if let Some(mut group) = groups.pop() {
+          group.directives.extend(directive_lines);
+          state = group.parent;
+          // A fully resolved group leaves no trace, so a declaration split across its branches stays intact
+          if !group.unknown || group.parent == State::Inactive {
+            group
+              .directives
+              .into_iter()
+              .for_each(|line| blank[line] = true);
+          }
+        }
+      }
+      _ => {
+        if state == State::Inactive {
+          directive_lines.for_each(|line| blank[line] = true);
+        } else if matches!(name, "define" | "undef") {
+          let macro_len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(rest.len());
+          let (macro_name, body) = rest.split_at(macro_len);
+          if state == State::Unknown {
+            // We can't tell whether this definition takes effect
+            macros.remove(macro_name);
+          } else if name == "undef" {
+            macros.insert(String::from(macro_name), Definition::Undefined);
+          } else if body.starts_with('(') {
+            macros.insert(String::from(macro_name), Definition::Function);
+          } else {
+            macros.insert(
+              String::from(macro_name),
+              Definition::Object(String::from(body.trim())),
+            );
+          }
+        }
+      }
+    }
+  }
+  for group in groups {
+    if group.parent == State::Inactive {
+      group
+        .directives
+        .into_iter()
+        .for_each(|line| blank[line] = true);
+    }
+  }
+
+  lines
+    .into_iter()
+    .zip(blank)
+    .map(|(line, blank)| {
+      if blank {
+        line
+          .bytes()
+          .map(|b| if b == b'\n' { '\n' } else { ' ' })
+          .collect::<String>()
+      } else {
+        String::from(line)
+      }
+    })
+    .collect()
+}
+
+fn enter(group: &mut Group, value: Option<bool>) -> State {
+  match value {
+    Some(true) => {
+      group.taken = true;
+      if group.unknown {
+        State::Unknown
+      } else {
+        group.parent
+      }
+    }
+    Some(false) => State::Inactive,
+    None => {
+      group.unknown = true;
+      State::Unknown
+    }
+  }
+}
+
+fn condition(name: &str, rest: &str, macros: &HashMap<String, Definition>) -> Option<bool> {
+  match name {
+    "ifdef" | "ifndef" => {
+      let defined = match macros.get(rest) {
+        Some(Definition::Undefined) => Some(false),
+        Some(_) => Some(true),
+        None => None,
+      };
+      if name.ends_with("ndef") {
+        defined.map(|defined| !defined)
+      } else {
+        defined
+      }
+    }
+    _ => Evaluator::new(rest, macros, 0)
+      .evaluate()
+      .map(|value| value != 0),
+  }
+}
+
+// Remove comments, returning whether a block comment is still open at the end of `text`
+fn skip_comments(text: &str, mut in_comment: bool) -> (String, bool) {
+  let mut code = String::new();
+  let mut quote = None;
+  let mut chars = text.chars().peekable();
+  while let Some(c) = chars.next() {
+    if in_comment {
+      if c == '*' && chars.peek() == Some(&'/') {
+        chars.next();
+        in_comment = false;
+        code.push(' ');
+      }
+    } else if let Some(q) = quote {
+      code.push(c);
+      if c == '\\' {
+        if let Some(c) = chars.next() {
+          code.push(c);
+        }
+      } else if c == q {
+        quote = None;
+      }
+    } else if c == '/' && chars.peek() == Some(&'*') {
+      chars.next();
+      in_comment = true;
+    } else if c == '/' && chars.peek() == Some(&'/') {
+      break;
+    } else {
+      if c == '"' || c == '\'' {
+        quote = Some(c);
+      }
+      code.push(c);
+    }
+  }
+  (code, in_comment)
+}
+
+fn tokenize(text: &str) -> Option<Vec<Token>> {
+  let mut tokens = Vec::new();
+  let mut rest = text.trim_start();
+  while !rest.is_empty() {
+    let c = rest.chars().next().unwrap();
+    let len = if c.is_ascii_alphabetic() || c == '_' {
+      let len = rest
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(rest.len());
+      tokens.push(Token::Identifier(String::from(&rest[..len])));
+      len
+    } else if c.is_ascii_digit() {
+      let len = rest
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '\'')
+        .unwrap_or(rest.len());
+      tokens.push(Token::Number(parse_number(&rest[..len])));
+      len
+    } else if c == '\'' {
+      let len = rest[1..].find('\'')? + 2;
+      let literal = &rest[1..len - 1];
+      let mut chars = literal.chars();
+      tokens.push(Token::Number(match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c as i64),
+        _ => None,
+      }));
+      len
+    } else {
+      let punctuator = PUNCTUATORS.iter().find(|p| rest.starts_with(*p))?;
+      tokens.push(Token::Punctuator(punctuator));
+      punctuator.len()
+    };
+    rest = rest[len..].trim_start();
+  }
+  Some(tokens)
+}
+
+fn parse_number(literal: &str) -> Option<i64> {
+  let literal = literal
+    .replace('\'', "")
+    .trim_end_matches(['u', 'U', 'l', 'L', 'z', 'Z'])
+    .to_lowercase();
+  let (digits, radix) = if let Some(digits) = literal.strip_prefix("0x") {
+    (digits, 16)
+  } else if let Some(digits) = literal.strip_prefix("0b") {
+    (digits, 2)
+  } else if literal.len() > 1 && literal.starts_with('0') {
+    (&literal[1..], 8)
+  } else {
+    (literal.as_str(), 10)
+  };
+  u64::from_str_radix(digits, radix)
+    .ok()
+    .map(|value| value as i64)
+}
+
+// Evaluate a `#if` expression with three-valued logic, where `None` means unknown
+struct Evaluator<'a> {
+  tokens: Vec<Token>,
+  pos: usize,
+  macros: &'a HashMap<String, Definition>,
+  depth: usize,
+  error: bool,
+}
+
+impl<'a> Evaluator<'a> {
+  fn new(text: &str, macros: &'a HashMap<String, Definition>, depth: usize) -> Self {
+    let tokens = tokenize(text);
+    Evaluator {
+      error: tokens.is_none(),
+      tokens: tokens.unwrap_or_default(),
+      pos: 0,
+      macros,
+      depth,
+    }
+  }
+
+  fn evaluate(&mut self) -> Option<i64> {
+    let value = self.conditional();
+    if self.error || self.pos != self.tokens.len() {
+      None
+    } else {
+      value
+    }
+  }
+
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.pos)
+  }
+
+  fn eat(&mut self, punctuator: &str) -> bool {
+    if matches!(self.peek(), Some(Token::Punctuator(p)) if *p == punctuator) {
+      self.pos += 1;
+      true
+    } else {
+      false
+    }
+  }
+
+  fn expect(&mut self, punctuator: &str) {
+    if !self.eat(punctuator) {
+      self.error = true;
+    }
+  }
+
+  fn conditional(&mut self) -> Option<i64> {
+    let condition = self.binary(1);
+    if self.eat("?") {
+      let then_value = self.conditional();
+      self.expect(":");
+      let else_value = self.conditional();
+      match condition {
+        Some(condition) if condition != 0 => then_value,
+        Some(_) => else_value,
+        None if then_value == else_value => then_value,
+        None => None,
+      }
+    } else {
+      condition
+    }
+  }
+
+  fn binary(&mut self, min_precedence: u8) -> Option<i64> {
+    let mut lhs = self.unary();
+    while let Some(Token::Punctuator(op)) = self.peek() {
+      let op = *op;
+      let precedence = match op {
+        "||" => 1,
+        "&&" => 2,
+        "|" => 3,
+        "^" => 4,
+        "&" => 5,
+        "==" | "!=" => 6,
+        "<" | ">" | "<=" | ">=" => 7,
+        "<<" | ">>" => 8,
+        "+" | "-" => 9,
+        "*" | "/" | "%" => 10,
+        _ => break,
+      };
+      if precedence < min_precedence {
+        break;
+      }
+      self.pos += 1;
+      let rhs = self.binary(precedence + 1);
+      lhs = match (op, lhs, rhs) {
+        ("&&", Some(0), _) | ("&&", _, Some(0)) => Some(0),
+        ("||", Some(l), _) | ("||", _, Some(l)) if l != 0 => Some(1),
+        (_, Some(l), Some(r)) => match op {
+          "||" => Some((l != 0 || r != 0) as i64),
+          "&&" => Some((l != 0 && r != 0) as i64),
+          "|" => Some(l | r),
+          "^" => Some(l ^ r),
+          "&" => Some(l & r),
+          "==" => Some((l == r) as i64),
+          "!=" => Some((l != r) as i64),
+          "<" => Some((l < r) as i64),
+          ">" => Some((l > r) as i64),
+          "<=" => Some((l <= r) as i64),
+          ">=" => Some((l >= r) as i64),
+          "<<" => l.checked_shl(r as u32),
+          ">>" => l.checked_shr(r as u32),
+          "+" => Some(l.wrapping_add(r)),
+          "-" => Some(l.wrapping_sub(r)),
+          "*" => Some(l.wrapping_mul(r)),
+          "/" => l.checked_div(r),
+          _ => l.checked_rem(r),
+        },
+        _ => None,
+      };
+    }
+    lhs
+  }
+
+  fn unary(&mut self) -> Option<i64> {
+    if self.eat("!") {
+      self.unary().map(|value| (value == 0) as i64)
+    } else if self.eat("~") {
+      self.unary().map(|value| !value)
+    } else if self.eat("-") {
+      self.unary().map(|value| value.wrapping_neg())
+    } else if self.eat("+") {
+      self.unary()
+    } else {
+      self.primary()
+    }
+  }
+
+  fn primary(&mut self) -> Option<i64> {
+    let token = match self.tokens.get(self.pos) {
+      Some(token) => token,
+      None => {
+        self.error = true;
+        return None;
+      }
+    };
+    self.pos += 1;
+    match token {
+      Token::Number(value) => *value,
+      Token::Punctuator("(") => {
+        let value = self.conditional();
+        self.expect(")");
+        value
+      }
+      Token::Identifier(name) if name == "defined" => {
+        let parenthesized = self.eat("(");
+        let value = match self.tokens.get(self.pos) {
+          Some(Token::Identifier(name)) => match self.macros.get(name) {
+            Some(Definition::Undefined) => Some(0),
+            Some(_) => Some(1),
+            None => None,
+          },
+          _ => {
+            self.error = true;
+            None
+          }
+        };
+        self.pos += 1;
+        if parenthesized {
+          self.expect(")");
+        }
+        value
+      }
+      Token::Identifier(name) if name == "true" => Some(1),
+      Token::Identifier(name) if name == "false" => Some(0),
+      Token::Identifier(name) => {
+        let definition = self.macros.get(name);
+        // Skip the arguments of function-like macros and builtins such as `__has_include`
+        if self.eat("(") {
+          let mut level = 1;
+          while level > 0 && self.pos < self.tokens.len() {
+            if self.eat("(") {
+              level += 1;
+            } else if self.eat(")") {
+              level -= 1;
+            } else {
+              self.pos += 1;
+            }
+          }
+          return None;
+        }
+        match definition {
+          Some(Definition::Undefined) => Some(0),
+          Some(Definition::Object(value)) if self.depth < MAX_DEPTH => {
+            Evaluator::new(value, self.macros, self.depth + 1).evaluate()
+          }
+          _ => None,
+        }
+      }
+      Token::Punctuator(_) => {
+        self.error = true;
+        None
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // The lines left for tree-sitter, with blanked out ones empty
+  fn evaluate_lines(source: &str, defines: &[(&str, &str)], undefines: &[&str]) -> Vec<String> {
+    let flags = compdb::CompileFlags {
+      defines: defines
+        .iter()
+        .map(|(name, value)| (String::from(*name), String::from(*value)))
+        .collect(),
+      undefines: undefines.iter().map(|name| String::from(*name)).collect(),
+      ..Default::default()
+    };
+    let text = evaluate(source, &flags);
+    assert_eq!(text.len(), source.len());
+    text.lines().map(|line| String::from(line.trim())).collect()
+  }
+
+  #[test]
+  fn blanks_out_inactive_branches() {
+    let source = "#ifdef FOO\nint a;\n#else\nint b;\n#endif\n";
+    assert_eq!(
+      evaluate_lines(source, &[("FOO", "1")], &[]),
+      ["", "int a;", "", "", ""]
+    );
+    assert_eq!(
+      evaluate_lines(source, &[], &["FOO"]),
+      ["", "", "", "int b;", ""]
+    );
+  }
+
+  #[test]
+  fn keeps_unknown_conditions() {
+    let source = "#if FOO > 1\nint a;\n#elif BAR\nint b;\n#endif\n";
+    assert_eq!(
+      evaluate_lines(source, &[], &[]),
+      ["#if FOO > 1", "int a;", "#elif BAR", "int b;", "#endif"]
+    );
+    // An unknown condition doesn't matter once another one is known
+    assert_eq!(
+      evaluate_lines("#if FOO || BAR\nint a;\n#endif\n", &[("BAR", "1")], &[]),
+      ["", "int a;", ""]
+    );
+    assert_eq!(
+      evaluate_lines("#if FOO && BAR\nint a;\n#endif\n", &[], &["BAR"]),
+      ["", "", ""]
+    );
+  }
+
+  #[test]
+  fn evaluates_expressions() {
+    let source =
+      "#if defined(FOO) && VERSION >= 0x10 && (1 << 2) == 4\nint a;\n#else\nint b;\n#endif\n";
+    assert_eq!(
+      evaluate_lines(source, &[("FOO", ""), ("VERSION", "16")], &[]),
+      ["", "int a;", "", "", ""]
+    );
+    assert_eq!(
+      evaluate_lines(source, &[("FOO", ""), ("VERSION", "010")], &[]),
+      ["", "", "", "int b;", ""]
+    );
+    assert_eq!(
+      evaluate_lines("#if LEVEL ? 1 : 1\nint a;\n#endif\n", &[], &[]),
+      ["", "int a;", ""]
+    );
+  }
+
+  #[test]
+  fn follows_definitions_of_the_file() {
+    let source =
+      "#define FOO 2\n#if FOO == 2\nint a;\n#endif\n#undef FOO\n#ifdef FOO\nint b;\n#endif\n";
+    assert_eq!(
+      evaluate_lines(source, &[], &[]),
+      ["#define FOO 2", "", "int a;", "", "#undef FOO", "", "", ""]
+    );
+    // A definition in a branch that may not be compiled makes the macro unknown
+    let source = "#ifdef BAR\n#define FOO\n#endif\n#ifdef FOO\nint a;\n#endif\n";
+    assert_eq!(
+      evaluate_lines(source, &[], &["FOO"]),
+      [
+        "#ifdef BAR",
+        "#define FOO",
+        "#endif",
+        "#ifdef FOO",
+        "int a;",
+        "#endif"
+      ]
+    );
+  }
+
+  #[test]
+  fn blanks_out_nested_groups_of_inactive_branches() {
+    let source = "#if 0\n#ifdef FOO\nint a;\n#else\nint b;\n#endif\n#endif\nint c;\n";
+    assert_eq!(
+      evaluate_lines(source, &[], &[]),
+      ["", "", "", "", "", "", "", "int c;"]
+    );
+  }
+
+  #[test]
+  fn joins_continued_directives() {
+    let source = "#if defined(FOO) \\\n  && 1 /* comment\n  */\nint a;\n#endif\n";
+    assert_eq!(
+      evaluate_lines(source, &[("FOO", "1")], &[]),
+      ["", "", "", "int a;", ""]
+    );
+    let source = "#if FOO /* a\n b\n c */ && 0\nint a;\n#endif\nint b;\n";
+    assert_eq!(
+      evaluate_lines(source, &[("FOO", "1")], &[]),
+      ["", "", "", "", "", "int b;"]
+    );
+  }
+}
@@ -0,0 +1,370 @@
+use crate::preprocessor::lexer::{is_blank, lex, lex_inline, Kind, Token};
+
+use std::collections::HashMap;
+
+struct Macro {
+  parameters: Option<Vec<String>>,
+  variadic: bool,
+  body: String,
+}
+
+#[derive(Default)]
+pub struct MacroTable {
+  macros: HashMap<String, Macro>,
+}
+
+const MAX_DEPTH: usize = 32;
+const VA_ARGS: &str = "__VA_ARGS__";
+
+impl MacroTable {
+  pub fn new() -> Self {
+    MacroTable::default()
+  }
+
+  // Record every `#define` in `source`. The first definition of a name wins, so the result only
+  // depends on the order in which files are collected.
+  pub fn collect(&mut self, source: &str) {
+    for (kind, text) in lex(source) {
+      if kind != Kind::Directive {
+        continue;
+      }
+      let directive = lex(&text.replace("\\\r\n", " ").replace("\\\n", " "))
+        .into_iter()
+        .map(|(kind, text)| if kind == Kind::Comment { " " } else { text })
+        .collect::<String>();
+      let directive = directive.trim_start()[1..].trim_start();
+      let rest = match directive.strip_prefix("define") {
+        Some(rest) if rest.starts_with(char::is_whitespace) => rest.trim_start(),
+        _ => continue,
+      };
+      let name_len = rest
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(rest.len());
+      let (name, rest) = rest.split_at(name_len);
+      if name.is_empty() || self.macros.contains_key(name) {
+        continue;
+      }
+      let mut variadic = false;
+      let (parameters, body) = match rest.strip_prefix('(') {
+        Some(rest) => match rest.split_once(')') {
+          Some((parameters, body)) => (
+            Some(
+              parameters
+                .split(',')
+                .map(|parameter| parameter.trim())
+                .filter(|parameter| !parameter.is_empty())
+                .map(|parameter| match parameter.strip_suffix("...") {
+                  Some(parameter) => {
+                    variadic = true;
+                    String::from(if parameter.is_empty() {
+                      VA_ARGS
+                    } else {
+                      parameter.trim()
+                    })
+                  }
+                  None => String::from(parameter),
+                })
+                .collect::<Vec<_>>(),
+            ),
+            body,
+          ),
+          None => continue,
+        },
+        None => (None, rest),
+      };
+      self.macros.insert(
+        String::from(name),
+        Macro {
+          parameters,
+          variadic,
+          body: String::from(body.trim()),
+        },
+      );
+    }
+  }
+
+  pub fn merge(&mut self, other: MacroTable) {
+    for (name, definition) in other.macros {
+      self.macros.entry(name).or_insert(definition);
+    }
+  }
+
+  // Identifies the set of definitions regardless of the order they were collected in
+  pub fn fingerprint(&self) -> String {
+    let mut names = self.macros.keys().collect::<Vec<_>>();
+    names.sort();
+    let mut hasher = blake3::Hasher::new();
+    for name in names {
+      let definition = &self.macros[name];
+      hasher.update(
+        format!(
+          "{}{:?}{}{}\0",
+          name, definition.parameters, definition.variadic, definition.body
+        )
+        .as_bytes(),
+      );
+    }
+    hasher.finalize().to_hex().to_string()
+  }
+
+  pub fn retain(&mut self, f: impl Fn(&str) -> bool) {
+    self.macros.retain(|name, _| f(name));
+  }
+
+  // Expand macro uses outside of preprocessor directives. An expansion is kept on the line of its
+  // use site, followed by the line breaks its arguments spanned, so rows stay intact.
+  pub fn expand(&self, source: &str) -> String {
+    self.expand_tokens(&lex(source), &mut vec![], 0)
+  }
+
+  fn expand_tokens(&self, tokens: &[Token], hidden: &mut Vec<String>, depth: usize) -> String {
+    let mut text = String::new();
+    let mut i = 0;
+    while i < tokens.len() {
+      let (kind, token) = tokens[i];
+      i += 1;
+      let definition = match self.macros.get(token) {
+        Some(definition)
+          if kind == Kind::Identifier && !hidden.iter().any(|name| name == token) =>
+        {
+          definition
+        }
+        _ => {
+          text.push_str(token);
+          continue;
+        }
+      };
+
+      let mut line_breaks = 0;
+      let arguments = match definition.parameters {
+        None => vec![],
+        Some(_) => match arguments(&tokens[i..]) {
+          None => {
+            text.push_str(token);
+            continue;
+          }
+          Some((arguments, len)) => {
+            line_breaks = tokens[i..i + len]
+              .iter()
+              .map(|(_, text)| text.matches('\n').count())
+              .sum();
+            i += len;
+            arguments
+          }
+        },
+      };
+
+      let replacement = if depth < MAX_DEPTH {
+        let replacement = self.substitute(definition, &arguments, hidden, depth);
+        hidden.push(String::from(token));
+        let replacement = self.expand_tokens(&lex_inline(&replacement), hidden, depth + 1);
+        hidden.pop();
+        replacement
+      } else {
+        String::from(token)
+      };
+      text.push_str(&replacement.replace(['\r', '\n'], " "));
+      text.push_str(&"\n".repeat(line_breaks));
+    }
+    text
+  }
+
+  // Replace parameters in the macro body, handling `#` stringification and `##` pasting
+  fn substitute(
+    &self,
+    definition: &Macro,
+    arguments: &[Vec<Token>],
+    hidden: &mut Vec<String>,
+    depth: usize,
+  ) -> String {
+    let parameters = definition.parameters.as_deref().unwrap_or_default();
+    let argument = |name: &str| -> Option<Vec<Token>> {
+      let index = parameters.iter().position(|parameter| parameter == name)?;
+      if definition.variadic && index + 1 == parameters.len() {
+        let mut variadic = vec![];
+        for (i, argument) in arguments.iter().enumerate().skip(index) {
+          if i > index {
+            variadic.push((Kind::Punctuator, ","));
+          }
+          variadic.extend_from_slice(argument);
+        }
+        Some(variadic)
+      } else {
+        Some(arguments.get(index).cloned().unwrap_or_default())
+      }
+    };
+    let raw = |tokens: &[Token]| tokens.iter().map(|(_, text)| *text).collect::<String>();
+
+    let body = lex_inline(&definition.body);
+    let significant = |from: usize| body[from..].iter().position(|(kind, _)| !is_blank(*kind));
+    let mut text = String::new();
+    let mut paste = false;
+    let mut i = 0;
+    while i < body.len() {
+      let (kind, token) = body[i];
+      i += 1;
+      if is_blank(kind) {
+        if !paste {
+          text.push_str(token);
+        }
+        continue;
+      }
+      if token == "##" {
+        text.truncate(text.trim_end().len());
+        paste = true;
+        continue;
+      }
+      let pasted = std::mem::take(&mut paste);
+      let next_is_paste = matches!(significant(i), Some(offset) if body[i + offset].1 == "##");
+
+      if token == "#" && definition.parameters.is_some() {
+        if let Some(offset) = significant(i) {
+          if let Some(tokens) = argument(body[i + offset].1) {
+            let stringified = raw(&tokens)
+              .trim()
+              .replace('\\', "\\\\")
+              .replace('"', "\\\"");
+            text.push_str(&format!("\"{}\"", stringified));
+            i += offset + 1;
+            continue;
+          }
+        }
+      }
+      match argument(token) {
+        Some(tokens) if pasted || next_is_paste => {
+          let tokens = raw(&tokens);
+          // GNU extension: `, ## __VA_ARGS__` drops the comma when no variadic argument is given
+          if pasted
+            && tokens.trim().is_empty()
+            && definition.variadic
+            && parameters
+              .last()
+              .is_some_and(|parameter| parameter == token)
+            && text.ends_with(',')
+          {
+            text.pop();
+          }
+          text.push_str(tokens.trim());
+        }
+        Some(tokens) => text.push_str(&self.expand_tokens(&tokens, hidden, depth + 1)),
+        None => text.push_str(token),
+      }
+    }
+    text
+  }
+}
+
+// Split the parenthesized arguments of a function-like macro use, returning them with the number
+// of tokens consumed. Returns `None` if the name is not followed by `(`.
+fn arguments<'a>(tokens: &[Token<'a>]) -> Option<(Vec<Vec<Token<'a>>>, usize)> {
+  let start = tokens.iter().position(|(kind, _)| !is_blank(*kind))?;
+  if tokens[start].1 != "(" {
+    return None;
+  }
+  let mut arguments = vec![vec![]];
+  let mut level = 0;
+  for (i, &(kind, text)) in tokens.iter().enumerate().skip(start + 1) {
+    match text {
+      "(" | "[" | "{" if kind == Kind::Punctuator => level += 1,
+      ")" if kind == Kind::Punctuator && level == 0 => {
+        if arguments.len() == 1 && arguments[0].iter().all(|(kind, _)| is_blank(*kind)) {
+          arguments.clear();
+        }
+        return Some((arguments, i + 1));
+      }
+      ")" | "]" | "}" if kind == Kind::Punctuator => level -= 1,
+      "," if kind == Kind::Punctuator && level == 0 => {
+        arguments.push(vec![]);
+        continue;
+      }
+      _ => (),
+    }
+    arguments.last_mut().unwrap().push((kind, text));
+  }
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn expand(definitions: &str, source: &str) -> String {
+    let mut macros = MacroTable::new();
+    macros.collect(definitions);
+    macros.expand(source)
+  }
+
+  #[test]
+  fn expands_object_like_macros() {
+    let definitions = "#define SIZE 16\n#define TWICE_SIZE (SIZE * 2)\n";
+    assert_eq!(
+      expand(definitions, "int a[TWICE_SIZE];"),
+      "int a[(16 * 2)];"
+    );
+    // Neither in directives, nor where only part of an identifier
+    assert_eq!(
+      expand(definitions, "#if SIZE\nint SIZE_MAX;\n"),
+      "#if SIZE\nint SIZE_MAX;\n"
+    );
+  }
+
+  #[test]
+  fn keeps_the_first_definition() {
+    assert_eq!(expand("#define A 1\n#define A 2\n", "A"), "1");
+    let mut macros = MacroTable::new();
+    macros.collect("#define A 1\n");
+    let mut other = MacroTable::new();
+    other.collect("#define A 3\n#define B 4\n");
+    macros.merge(other);
+    assert_eq!(macros.expand("A B"), "1 4");
+  }
+
+  #[test]
+  fn expands_function_like_macros() {
+    let definitions =
+      "#define MAX(a, b) ((a) > (b) ? (a) : (b))\n#define CALL(f, ...) f(__VA_ARGS__)\n";
+    assert_eq!(
+      expand(definitions, "MAX(x, f(y, z))"),
+      "((x) > ( f(y, z)) ? (x) : ( f(y, z)))"
+    );
+    assert_eq!(expand(definitions, "CALL(g, 1, 2)"), "g( 1, 2)");
+    // Not a use without arguments
+    assert_eq!(expand(definitions, "int MAX;"), "int MAX;");
+  }
+
+  #[test]
+  fn keeps_rows_of_arguments_spanning_lines() {
+    let definitions = "#define ID(x) x\n";
+    assert_eq!(
+      expand(definitions, "ID(f(1,\n2));\nint a;"),
+      "f(1, 2)\n;\nint a;"
+    );
+  }
+
+  #[test]
+  fn pastes_and_stringifies() {
+    let definitions = "#define GETTER(name) int get_##name()\n#define STR(x) #x\n#define LOG(fmt, ...) log(fmt, ## __VA_ARGS__)\n";
+    assert_eq!(expand(definitions, "GETTER(size);"), "int get_size();");
+    assert_eq!(expand(definitions, "STR(a \"b\")"), "\"a \\\"b\\\"\"");
+    assert_eq!(expand(definitions, "LOG(\"x\", 1)"), "log(\"x\",1)");
+    assert_eq!(expand(definitions, "LOG(\"x\")"), "log(\"x\")");
+  }
+
+  #[test]
+  fn stops_at_recursive_macros() {
+    let definitions = "#define A B + 1\n#define B A + 2\n#define f(x) f(x + 1)\n";
+    assert_eq!(expand(definitions, "A"), "A + 2 + 1");
+    assert_eq!(expand(definitions, "f(0)"), "f(0 + 1)");
+  }
+
+  #[test]
+  fn fingerprints_regardless_of_order() {
+    let mut macros = MacroTable::new();
+    macros.collect("#define A 1\n#define B(x) x\n");
+    let mut other = MacroTable::new();
+    other.collect("#define B(x) x\n#define A 1\n");
+    assert_eq!(macros.fingerprint(), other.fingerprint());
+    other.retain(|name| name != "B");
+    assert_ne!(macros.fingerprint(), other.fingerprint());
+  }
+}
@@ -0,0 +1,262 @@
+use crate::preprocessor::lexer::{is_blank, lex, Kind, Token};
+
+use anyhow::Result;
+use regex::Regex;
+use regex_syntax::hir::{Class, Hir, HirKind};
+
+// A macro to ignore, matched against whole tokens: either tokens as written (e.g., `MAYBE_UNUSED`,
+// `DLL_EXPORT(core)` or `EXPORT API`), or with a `re:` prefix, a regex matched against single
+// identifiers (e.g., `re:.*_EXPORT`). Both may be followed by `(...)` to ignore the balanced
+// argument list after them as well (e.g., `GUARDED_BY(...)`).
+pub struct IgnoreRule {
+  pattern: Pattern,
+  arguments: bool,
+}
+
+enum Pattern {
+  Tokens(Vec<String>),
+  Regex(Regex),
+}
+
+impl IgnoreRule {
+  pub fn new(rule: &str) -> Result<Self> {
+    let (pattern, arguments) = match rule
+      .strip_suffix("(...)")
+      .or_else(|| rule.strip_suffix("()"))
+    {
+      Some(pattern) => (pattern, true),
+      None => (rule, false),
+    };
+    let pattern = match pattern.strip_prefix("re:") {
+      Some(pattern) => {
+        let hir = regex_syntax::parse(pattern)
+          .map_err(|e| anyhow::anyhow!("Invalid --ignore-macro \"{}\": {}", rule, e))?;
+        if !matches_identifier(&hir) {
+          return Err(anyhow::anyhow!(
+            "--ignore-macro \"{}\" never matches, since regexes are matched against single identifiers",
+            rule
+          ));
+        }
+        Pattern::Regex(Regex::new(&format!("^(?:{})$", pattern))?)
+      }
+      None => {
+        let tokens = lex(pattern)
+          .into_iter()
+          .filter(|(kind, _)| !is_blank(*kind))
+          .map(|(_, token)| String::from(token))
+          .collect::<Vec<_>>();
+        if tokens.is_empty() {
+          return Err(anyhow::anyhow!("--ignore-macro \"{}\" is empty", rule));
+        }
+        Pattern::Tokens(tokens)
+      }
+    };
+    Ok(IgnoreRule { pattern, arguments })
+  }
+
+  // Whether the rule ignores the macro of the given name wherever it is used
+  pub fn is_match(&self, name: &str) -> bool {
+    match &self.pattern {
+      Pattern::Tokens(tokens) => tokens.len() == 1 && tokens[0] == name,
+      Pattern::Regex(regex) => regex.is_match(name),
+    }
+  }
+
+  // Number of tokens matched at the start of the given tokens, skipping blanks between them
+  fn match_len(&self, tokens: &[Token]) -> Option<usize> {
+    match &self.pattern {
+      Pattern::Regex(regex) => match tokens.first()? {
+        (Kind::Identifier, token) if regex.is_match(token) => Some(1),
+        _ => None,
+      },
+      Pattern::Tokens(pattern) => {
+        let mut len = 0;
+        for expected in pattern {
+          if len > 0 {
+            len += tokens[len..]
+              .iter()
+              .take_while(|(kind, _)| is_blank(*kind))
+              .count();
+          }
+          match tokens.get(len) {
+            Some((kind, token)) if *kind != Kind::Directive && token == expected => len += 1,
+            _ => return None,
+          }
+        }
+        Some(len)
+      }
+    }
+  }
+}
+
+// Whether a regex may match an identifier, i.e., a string of word characters
+fn matches_identifier(hir: &Hir) -> bool {
+  let is_word = |start: u32, end: u32| {
+    [
+      ('0', '9'),
+      ('A', 'Z'),
+      ('_', '_'),
+      ('a', 'z'),
+      ('\u{80}', char::MAX),
+    ]
+    .iter()
+    .any(|(first, last)| start <= *last as u32 && *first as u32 <= end)
+  };
+  match hir.kind() {
+    HirKind::Empty | HirKind::Look(_) => true,
+    HirKind::Literal(literal) => literal
+      .0
+      .iter()
+      .all(|&b| b.is_ascii_alphanumeric() || b == b'_' || b >= 0x80),
+    HirKind::Class(Class::Unicode(class)) => class
+      .ranges()
+      .iter()
+      .any(|range| is_word(range.start() as u32, range.end() as u32)),
+    HirKind::Class(Class::Bytes(class)) => class
+      .ranges()
+      .iter()
+      .any(|range| is_word(range.start() as u32, range.end() as u32)),
+    HirKind::Repetition(repetition) => repetition.min == 0 || matches_identifier(&repetition.sub),
+    HirKind::Capture(capture) => matches_identifier(&capture.sub),
+    HirKind::Concat(hirs) => hirs.iter().all(matches_identifier),
+    HirKind::Alternation(hirs) => hirs.iter().any(matches_identifier),
+  }
+}
+
+// Replace ignored macros with blanks of the same length outside of preprocessor directives,
+// so byte offsets and rows stay intact
+pub fn apply(source: &str, rules: &[IgnoreRule]) -> String {
+  if rules.is_empty() {
+    return String::from(source);
+  }
+
+  let tokens = lex(source);
+  let mut text = String::with_capacity(source.len());
+  let mut i = 0;
+  while i < tokens.len() {
+    match rules
+      .iter()
+      .find_map(|rule| Some((rule, rule.match_len(&tokens[i..])?)))
+    {
+      None => {
+        text.push_str(tokens[i].1);
+        i += 1;
+      }
+      Some((rule, mut len)) => {
+        if rule.arguments {
+          len += argument_list(&tokens[i + len..]).unwrap_or(0);
+        }
+        tokens[i..i + len]
+          .iter()
+          .for_each(|(_, token)| text.push_str(&blank(token)));
+        i += len;
+      }
+    }
+  }
+  text
+}
+
+fn blank(text: &str) -> String {
+  text
+    .bytes()
+    .map(|b| if b == b'\n' { '\n' } else { ' ' })
+    .collect()
+}
+
+// Number of tokens up to the `)` closing the argument list that starts the given tokens
+fn argument_list(tokens: &[Token]) -> Option<usize> {
+  let start = tokens.iter().position(|(kind, _)| !is_blank(*kind))?;
+  if tokens[start].1 != "(" {
+    return None;
+  }
+  let mut level = 0;
+  for (i, (kind, token)) in tokens.iter().enumerate().skip(start) {
+    if *kind == Kind::Punctuator {
+      match *token {
+        "(" => level += 1,
+        ")" => {
+          level -= 1;
+          if level == 0 {
+            return Some(i + 1);
+          }
+        }
+        _ => (),
+      }
+    }
+  }
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn ignore(source: &str, rules: &[&str]) -> String {
+    let rules = rules
+      .iter()
+      .map(|rule| IgnoreRule::new(rule).unwrap())
+      .collect::<Vec<_>>();
+    let text = apply(source, &rules);
+    assert_eq!(text.len(), source.len());
+    text
+  }
+
+  #[test]
+  fn matches_whole_tokens() {
+    assert_eq!(
+      ignore("API void MY_API_HELPER();", &["API"]),
+      "    void MY_API_HELPER();"
+    );
+  }
+
+  #[test]
+  fn matches_token_sequences() {
+    assert_eq!(
+      ignore("class DLL_EXPORT( core ) Foo {};", &["DLL_EXPORT(core)"]),
+      "class                    Foo {};"
+    );
+    assert_eq!(
+      ignore("class EXPORT\n  API Baz : Bar {};", &["EXPORT API"]),
+      "class       \n      Baz : Bar {};"
+    );
+    assert_eq!(ignore("EXPORT int x;", &["EXPORT API"]), "EXPORT int x;");
+  }
+
+  #[test]
+  fn ignores_argument_lists() {
+    assert_eq!(
+      ignore("int x GUARDED_BY(f(mu_)) = 0;", &["GUARDED_BY(...)"]),
+      "int x                    = 0;"
+    );
+    assert_eq!(ignore("GUARDED_BY;", &["GUARDED_BY(...)"]), "          ;");
+  }
+
+  #[test]
+  fn matches_regexes_against_identifiers() {
+    assert_eq!(
+      ignore("class LEVELDB_EXPORT DB {};", &["re:.*_EXPORT"]),
+      "class                DB {};"
+    );
+    assert_eq!(
+      ignore("class EXPORT DB {};", &["re:.*_EXPORT"]),
+      "class EXPORT DB {};"
+    );
+  }
+
+  #[test]
+  fn leaves_directives_alone() {
+    assert_eq!(
+      ignore("#define API\nAPI int x;", &["API"]),
+      "#define API\n    int x;"
+    );
+  }
+
+  #[test]
+  fn rejects_rules_matching_nothing() {
+    assert!(IgnoreRule::new("re:EXPORT API").is_err());
+    assert!(IgnoreRule::new(r"re:DLL_EXPORT\(core\)").is_err());
+    assert!(IgnoreRule::new("re:(").is_err());
+    assert!(IgnoreRule::new(" ").is_err());
+    assert!(IgnoreRule::new("re:[A-Z]+_API|::").is_ok());
+  }
+}
@@ -0,0 +1,112 @@
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Kind {
+  Identifier,
+  Whitespace,
+  Comment,
+  Literal,
+  Directive,
+  Punctuator,
+}
+
+pub type Token<'a> = (Kind, &'a str);
+
+pub fn is_blank(kind: Kind) -> bool {
+  matches!(kind, Kind::Whitespace | Kind::Comment)
+}
+
+// Split source into tokens that concatenate back to the original text
+pub fn lex(source: &str) -> Vec<Token<'_>> {
+  lex_from(source, true)
+}
+
+// Like `lex`, for text that doesn't start a line, such as a macro body, where a leading `#` is the
+// stringification operator rather than a directive
+pub fn lex_inline(source: &str) -> Vec<Token<'_>> {
+  lex_from(source, false)
+}
+
+fn lex_from(source: &str, mut line_start: bool) -> Vec<Token<'_>> {
+  let bytes = source.as_bytes();
+  let mut tokens = vec![];
+  let mut i = 0;
+  while i < bytes.len() {
+    let c = bytes[i];
+    let start = i;
+    let kind = if c.is_ascii_whitespace() {
+      while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+        line_start |= bytes[i] == b'\n';
+        i += 1;
+      }
+      tokens.push((Kind::Whitespace, &source[start..i]));
+      continue;
+    } else if c == b'#' && line_start {
+      // A directive runs to the end of the line, including continuation lines
+      while i < bytes.len() && bytes[i] != b'\n' {
+        if bytes[i] == b'\\' && i + 1 < bytes.len() {
+          i += 1;
+        } else if bytes[i] == b'/' && bytes.get(i + 1) == Some(&b'*') {
+          i = source[i + 2..]
+            .find("*/")
+            .map_or(bytes.len(), |end| i + end + 4);
+          continue;
+        }
+        i += 1;
+      }
+      Kind::Directive
+    } else if c == b'/' && bytes.get(i + 1) == Some(&b'/') {
+      while i < bytes.len() && bytes[i] != b'\n' {
+        i += if bytes[i] == b'\\' { 2 } else { 1 };
+      }
+      Kind::Comment
+    } else if c == b'/' && bytes.get(i + 1) == Some(&b'*') {
+      i = source[i + 2..]
+        .find("*/")
+        .map_or(bytes.len(), |end| i + end + 4);
+      Kind::Comment
+    } else if c.is_ascii_alphabetic() || c == b'_' || c >= 0x80 {
+      while i < bytes.len()
+        && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] >= 0x80)
+      {
+        i += 1;
+      }
+      if bytes.get(i) == Some(&b'"') && source[start..i].ends_with('R') {
+        // Raw string literal, e.g., R"delimiter(...)delimiter"
+        let delimiter_end = source[i..].find('(').map_or(bytes.len(), |end| i + end);
+        let terminator = format!("){}\"", &source[i + 1..delimiter_end]);
+        i = source[delimiter_end..]
+          .find(&terminator)
+          .map_or(bytes.len(), |end| delimiter_end + end + terminator.len());
+        Kind::Literal
+      } else {
+        Kind::Identifier
+      }
+    } else if c.is_ascii_digit() || (c == b'.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+    {
+      while i < bytes.len()
+        && (bytes[i].is_ascii_alphanumeric()
+          || matches!(bytes[i], b'_' | b'.' | b'\'')
+          || (matches!(bytes[i], b'+' | b'-') && matches!(bytes[i - 1], b'e' | b'E' | b'p' | b'P')))
+      {
+        i += 1;
+      }
+      Kind::Punctuator
+    } else if c == b'"' || c == b'\'' {
+      i += 1;
+      while i < bytes.len() && bytes[i] != c && bytes[i] != b'\n' {
+        i += if bytes[i] == b'\\' { 2 } else { 1 };
+      }
+      i = bytes.len().min(i + 1);
+      Kind::Literal
+    } else if c == b'#' && bytes.get(i + 1) == Some(&b'#') {
+      i += 2;
+      Kind::Punctuator
+    } else {
+      i += 1;
+      Kind::Punctuator
+    };
+    line_start = false;
+    i = i.min(bytes.len());
+    tokens.push((kind, &source[start..i]));
+  }
+  tokens
+}
@@ -0,0 +1,52 @@
+pub mod conditional;
+pub mod expansion;
+pub mod ignore;
+pub mod lexer;
+
+use crate::compdb;
+
+pub struct Preprocessor {
+  ignore_rules: Vec<ignore::IgnoreRule>,
+  macros: Option<expansion::MacroTable>,
+}
+
+impl Preprocessor {
+  pub fn new(ignore_macros: &[String]) -> Self {
+    Preprocessor {
+      ignore_rules: ignore_macros
+        .iter()
+        .filter_map(|ignore_macro| {
+          ignore::IgnoreRule::new(ignore_macro)
+            .inspect_err(|e| eprintln!("[Warning] {}", e))
+            .ok()
+        })
+        .collect(),
+      macros: None,
+    }
+  }
+
+  // Ignored macros are never expanded, they are blanked out instead
+  pub fn set_macros(&mut self, mut macros: expansion::MacroTable) {
+    macros.retain(|name| !self.ignore_rules.iter().any(|rule| rule.is_match(name)));
+    self.macros = Some(macros);
+  }
+
+  pub fn run(&self, mut source: String, flags: &compdb::CompileFlags) -> String {
+    source = conditional::evaluate(&source, flags);
+
+    // Macros defined to nothing on the command line (e.g., -DFOO_EXPORT=) are ignored as well
+    let empty_defines = flags
+      .defines
+      .iter()
+      .filter(|(_, value)| value.is_empty())
+      .flat_map(|(name, _)| ignore::IgnoreRule::new(name))
+      .collect::<Vec<_>>();
+    source = ignore::apply(&source, &self.ignore_rules);
+    source = ignore::apply(&source, &empty_defines);
+
+    if let Some(ref macros) = self.macros {
+      source = macros.expand(&source);
+    }
+    source
+  }
+}
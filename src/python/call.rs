@@ -0,0 +1,190 @@
+use crate::analyzer;
+use crate::graph;
+use crate::node;
+use crate::syntaxtree;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path;
+use std::sync::OnceLock;
+
+use super::Scope;
+
+type Call = node::Node;
+
+pub struct CallAnalyzer {
+  packages: super::Packages,
+  method_pool: OnceLock<HashMap<String, Call>>,
+}
+
+// The calls made by the functions of a file, in the order they are found
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Calls {
+  calls: Vec<(String, Callee)>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+enum Callee {
+  // e.g., `f()` or `mod.f()`, along with the qualified names it may refer to, innermost first
+  Function(Vec<String>, String),
+  // e.g., `x.f()`, along with the class of `x` if it is `self` or `cls`
+  Method(Option<String>, String),
+}
+
+impl CallAnalyzer {
+  pub fn new() -> Self {
+    CallAnalyzer {
+      packages: super::Packages::new(),
+      method_pool: OnceLock::new(),
+    }
+  }
+}
+
+// Names are looked up in the enclosing functions, then at the top level of the module (class
+// scopes are skipped, as in Python)
+fn callee(
+  syntax_tree: &syntaxtree::SyntaxTree,
+  names: &super::Names,
+  function: &tree_sitter::Node,
+  scopes: &[(usize, Scope)],
+) -> Option<Callee> {
+  match function.kind() {
+    "identifier" => {
+      let name = syntax_tree.source(function);
+      let candidates = scopes
+        .iter()
+        .rev()
+        .filter_map(|(_, scope)| match scope {
+          Scope::Function(function) => Some(format!("{}.{}", function, name)),
+          _ => None,
+        })
+        .chain(names.resolve(name))
+        .collect();
+      Some(Callee::Function(candidates, String::from(name)))
+    }
+    "attribute" => {
+      let name = syntax_tree.source(&function.child_by_field_name("attribute")?);
+      let object = function.child_by_field_name("object")?;
+      if matches!(syntax_tree.source(&object), "self" | "cls") {
+        let class = scopes.iter().rev().find_map(|(_, scope)| match scope {
+          Scope::Class(class) => Some(class.clone()),
+          _ => None,
+        });
+        return Some(Callee::Method(class, String::from(name)));
+      }
+      match super::dotted_name(syntax_tree, function)
+        .and_then(|dotted_name| Some((names.resolve(&dotted_name)?, dotted_name)))
+      {
+        Some((qualified_name, dotted_name)) => {
+          Some(Callee::Function(vec![qualified_name], dotted_name))
+        }
+        None => Some(Callee::Method(None, String::from(name))),
+      }
+    }
+    _ => None,
+  }
+}
+
+impl analyzer::Analyzer for CallAnalyzer {
+  type Summary = Calls;
+
+  fn name(&self) -> &'static str {
+    "call"
+  }
+
+  fn set_revision_files(&self, files: &[&path::Path]) {
+    self.packages.set_revision_files(files);
+  }
+
+  fn extract_nodes(&self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph) {
+    let module = self.packages.module_name(&syntax_tree.file);
+    super::walk(syntax_tree, &module, |node, scopes| {
+      if node.kind() != "function_definition" {
+        return;
+      }
+      if let Some(name) = node.child_by_field_name("name") {
+        graph.add_node(&Call::new(
+          &format!(
+            "{}()",
+            super::qualify(syntax_tree.source(&name), &module, scopes)
+          ),
+          &syntax_tree.file,
+          name.start_position().row + 1,
+        ));
+      }
+    });
+  }
+
+  fn summarize(&self, syntax_tree: &syntaxtree::SyntaxTree) -> Calls {
+    let module = self.packages.module_name(&syntax_tree.file);
+    let names = super::Names::new(syntax_tree, &module);
+    let mut calls = vec![];
+    super::walk(syntax_tree, &module, |node, scopes| {
+      if node.kind() != "call" {
+        return;
+      }
+      let caller = scopes.iter().rev().find_map(|(_, scope)| match scope {
+        Scope::Function(function) => Some(function),
+        _ => None,
+      });
+      if let (Some(caller), Some(callee)) = (
+        caller,
+        node
+          .child_by_field_name("function")
+          .and_then(|function| callee(syntax_tree, &names, &function, scopes)),
+      ) {
+        calls.push((format!("{}()", caller), callee));
+      }
+    });
+    Calls { calls }
+  }
+
+  fn extract_edges(&self, calls: &Calls, graph: &graph::Graph, partial_graph: &mut graph::Graph) {
+    let method_pool = self.method_pool.get_or_init(|| {
+      let mut method_pool = HashMap::<String, Call>::new();
+      for (qualified_name, call) in graph.nodes.iter() {
+        if let Some(index) = qualified_name.rfind('.') {
+          method_pool
+            .entry(String::from(&qualified_name[index + 1..]))
+            .and_modify(|method| method.merge_node(call))
+            .or_insert_with(|| call.clone());
+        }
+      }
+      method_pool
+    });
+
+    for (caller, callee) in calls.calls.iter() {
+      let call = match graph.get_node(caller) {
+        Some(call) => call,
+        None => continue,
+      };
+      // Calling a class (e.g., `Foo()`) calls its constructor
+      let (callee, name) = match callee {
+        Callee::Function(candidates, name) => (
+          candidates.iter().find_map(|candidate| {
+            graph
+              .get_node(&format!("{}()", candidate))
+              .or_else(|| graph.get_node(&format!("{}.__init__()", candidate)))
+          }),
+          format!("{}()", name),
+        ),
+        Callee::Method(class, name) => {
+          let method = format!("{}()", name);
+          let callee = class
+            .as_ref()
+            .and_then(|class| graph.get_node(&format!("{}.{}", class, method)))
+            .or_else(|| method_pool.get(&method));
+          (callee, method)
+        }
+      };
+      match callee {
+        Some(callee) => partial_graph.add_edge(call, callee),
+        None => {
+          let callee = Call::new_without_loc(&name);
+          partial_graph.add_node(&callee);
+          partial_graph.add_edge(call, &callee);
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,97 @@
+use crate::analyzer;
+use crate::graph;
+use crate::node;
+use crate::syntaxtree;
+
+use std::path;
+
+type Class = node::Node;
+
+pub struct ClassAnalyzer {
+  packages: super::Packages,
+}
+
+impl ClassAnalyzer {
+  pub fn new() -> Self {
+    ClassAnalyzer {
+      packages: super::Packages::new(),
+    }
+  }
+}
+
+impl analyzer::Analyzer for ClassAnalyzer {
+  type Summary = Vec<analyzer::Inheritance>;
+
+  fn name(&self) -> &'static str {
+    "class"
+  }
+
+  fn set_revision_files(&self, files: &[&path::Path]) {
+    self.packages.set_revision_files(files);
+  }
+
+  fn extract_nodes(&self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph) {
+    let module = self.packages.module_name(&syntax_tree.file);
+    super::walk(syntax_tree, &module, |node, scopes| {
+      if node.kind() != "class_definition" {
+        return;
+      }
+      if let Some(name) = node.child_by_field_name("name") {
+        graph.add_node(&Class::new(
+          &super::qualify(syntax_tree.source(&name), &module, scopes),
+          &syntax_tree.file,
+          name.start_position().row + 1,
+        ));
+      }
+    });
+  }
+
+  // Base classes are qualified by the imports of the module when possible, e.g., `pkg.Base` for
+  // `class Foo(Base)` after `from pkg import Base`. `object` and keyword arguments (e.g.,
+  // `metaclass=ABCMeta`) are not base classes to show.
+  fn summarize(&self, syntax_tree: &syntaxtree::SyntaxTree) -> Vec<analyzer::Inheritance> {
+    let module = self.packages.module_name(&syntax_tree.file);
+    let names = super::Names::new(syntax_tree, &module);
+    let mut inheritances = Vec::<analyzer::Inheritance>::new();
+    super::walk(syntax_tree, &module, |node, scopes| {
+      if node.kind() != "class_definition" {
+        return;
+      }
+      if let (Some(name), Some(superclasses)) = (
+        node.child_by_field_name("name"),
+        node.child_by_field_name("superclasses"),
+      ) {
+        let mut cursor = superclasses.walk();
+        let baseclasses = superclasses
+          .named_children(&mut cursor)
+          .filter_map(|baseclass| match baseclass.kind() {
+            // e.g., `Generic[T]`
+            "subscript" => {
+              super::dotted_name(syntax_tree, &baseclass.child_by_field_name("value")?)
+            }
+            _ => super::dotted_name(syntax_tree, &baseclass),
+          })
+          .filter(|baseclass| baseclass != "object")
+          .map(|baseclass| names.resolve(&baseclass).unwrap_or(baseclass))
+          .collect::<Vec<_>>();
+        if !baseclasses.is_empty() {
+          inheritances.push(analyzer::Inheritance {
+            class: super::qualify(syntax_tree.source(&name), &module, scopes),
+            baseclasses,
+            attributes: vec![],
+          });
+        }
+      }
+    });
+    inheritances
+  }
+
+  fn extract_edges(
+    &self,
+    inheritances: &Vec<analyzer::Inheritance>,
+    graph: &graph::Graph,
+    partial_graph: &mut graph::Graph,
+  ) {
+    analyzer::extract_inheritance_edges(inheritances, graph, partial_graph);
+  }
+}
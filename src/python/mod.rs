@@ -0,0 +1,246 @@
+pub mod call;
+pub mod class;
+
+use crate::analyzer;
+use crate::language::{self, Grammar};
+use crate::syntaxtree;
+
+use std::collections::{HashMap, HashSet};
+use std::path;
+use std::sync::OnceLock;
+
+pub struct Python;
+
+impl language::Language for Python {
+  fn grammars(&self) -> &'static [(&'static str, Grammar)] {
+    &[("python", Grammar::Python), ("py", Grammar::Python)]
+  }
+
+  fn extensions(&self) -> &'static [(&'static str, Grammar)] {
+    &[("py", Grammar::Python)]
+  }
+
+  fn tree_sitter(&self, _grammar: Grammar) -> tree_sitter::Language {
+    tree_sitter_python::language()
+  }
+
+  fn analyzer(&self, kind: analyzer::Kind) -> Option<Box<dyn analyzer::AnyAnalyzer>> {
+    match kind {
+      analyzer::Kind::Call => Some(Box::new(call::CallAnalyzer::new())),
+      analyzer::Kind::Class => Some(Box::new(class::ClassAnalyzer::new())),
+      analyzer::Kind::Include => None,
+    }
+  }
+}
+
+// The classes and functions enclosing a node by their qualified name, each along with the byte
+// where it ends
+enum Scope {
+  Class(String),
+  Function(String),
+}
+
+// Where modules find their packages: the working tree, or the files of the commit analyzed by
+// `--rev`, named relative to the repository
+struct Packages {
+  revision_files: OnceLock<HashSet<path::PathBuf>>,
+}
+
+impl Packages {
+  fn new() -> Self {
+    Packages {
+      revision_files: OnceLock::new(),
+    }
+  }
+
+  fn set_revision_files(&self, files: &[&path::Path]) {
+    let _ = self
+      .revision_files
+      .set(files.iter().map(|file| file.to_path_buf()).collect());
+  }
+
+  fn is_package(&self, dir: &path::Path) -> bool {
+    let init = dir.join("__init__.py");
+    match self.revision_files.get() {
+      Some(files) => files.contains(&init),
+      None => init.is_file(),
+    }
+  }
+
+  // The module defined by a file, e.g., `pkg.mod` for `pkg/mod.py` if there is a `pkg/__init__.py`
+  fn module_name(&self, file: &str) -> String {
+    let file = path::Path::new(file);
+    let file = match self.revision_files.get() {
+      Some(_) => file.to_path_buf(),
+      None => file.canonicalize().unwrap_or_else(|_| file.to_path_buf()),
+    };
+    let stem = file
+      .file_stem()
+      .map(|stem| stem.to_string_lossy().into_owned())
+      .unwrap_or_default();
+    let mut names = vec![];
+    if stem != "__init__" {
+      names.push(stem.clone());
+    }
+    let mut dir = file.parent();
+    while let Some(package) = dir.filter(|dir| self.is_package(dir)) {
+      match package.file_name() {
+        Some(name) => names.push(name.to_string_lossy().into_owned()),
+        None => break,
+      }
+      dir = package.parent();
+    }
+    if names.is_empty() {
+      return stem;
+    }
+    names.reverse();
+    names.join(".")
+  }
+}
+
+// Walk the definitions of a file, calling `visit` with the scopes around each node. Classes and
+// functions are qualified by the module and the scopes they are defined in (e.g., `pkg.mod.A.f`).
+fn walk<'a>(
+  syntax_tree: &'a syntaxtree::SyntaxTree,
+  module: &str,
+  mut visit: impl FnMut(&tree_sitter::Node<'a>, &[(usize, Scope)]),
+) {
+  let mut scopes = Vec::<(usize, Scope)>::new();
+  for node in syntax_tree.iter() {
+    while scopes
+      .last()
+      .is_some_and(|(end, _)| node.start_byte() >= *end)
+    {
+      scopes.pop();
+    }
+    visit(&node, &scopes);
+
+    let new_scope = match node.kind() {
+      "class_definition" => Scope::Class,
+      "function_definition" => Scope::Function,
+      _ => continue,
+    };
+    if let Some(name) = node.child_by_field_name("name") {
+      let name = qualify(syntax_tree.source(&name), module, &scopes);
+      scopes.push((node.end_byte(), new_scope(name)));
+    }
+  }
+}
+
+fn qualify(name: &str, module: &str, scopes: &[(usize, Scope)]) -> String {
+  match scopes.last() {
+    Some((_, Scope::Class(scope) | Scope::Function(scope))) => format!("{}.{}", scope, name),
+    None => format!("{}.{}", module, name),
+  }
+}
+
+// A name or an attribute of names (e.g., `os.path.join`), as written
+fn dotted_name(syntax_tree: &syntaxtree::SyntaxTree, node: &tree_sitter::Node) -> Option<String> {
+  match node.kind() {
+    "identifier" => Some(String::from(syntax_tree.source(node))),
+    "attribute" => Some(format!(
+      "{}.{}",
+      dotted_name(syntax_tree, &node.child_by_field_name("object")?)?,
+      syntax_tree.source(&node.child_by_field_name("attribute")?)
+    )),
+    _ => None,
+  }
+}
+
+// The qualified names bound at the top level of a module, i.e., those of its classes and functions
+// and, wherever they are, those of its imports (e.g., `j` for `from os.path import join as j`)
+struct Names {
+  names: HashMap<String, String>,
+}
+
+impl Names {
+  fn new(syntax_tree: &syntaxtree::SyntaxTree, module: &str) -> Self {
+    let package = match module.rsplit_once('.') {
+      _ if syntax_tree.file.ends_with("__init__.py") => module,
+      Some((package, _)) => package,
+      None => "",
+    };
+    let mut names = HashMap::new();
+    walk(syntax_tree, module, |node, scopes| {
+      let mut cursor = node.walk();
+      match node.kind() {
+        "class_definition" | "function_definition" if scopes.is_empty() => {
+          if let Some(name) = node.child_by_field_name("name") {
+            let name = syntax_tree.source(&name);
+            names.insert(String::from(name), format!("{}.{}", module, name));
+          }
+        }
+        // `import a.b` binds `a`, while `import a.b as c` binds `c` to `a.b`
+        "import_statement" => {
+          for name in node.children_by_field_name("name", &mut cursor) {
+            let (name, alias) = match name.child_by_field_name("alias") {
+              Some(alias) => (
+                syntax_tree.source(&name.child_by_field_name("name").unwrap_or(name)),
+                syntax_tree.source(&alias),
+              ),
+              None => {
+                let name = syntax_tree.source(&name);
+                let package = name.split('.').next().unwrap_or(name);
+                (package, package)
+              }
+            };
+            names.insert(String::from(alias), String::from(name));
+          }
+        }
+        "import_from_statement" => {
+          let from = match node.child_by_field_name("module_name") {
+            Some(from) => relative_module(syntax_tree.source(&from), package),
+            None => return,
+          };
+          for name in node.children_by_field_name("name", &mut cursor) {
+            let (name, alias) = match name.child_by_field_name("alias") {
+              Some(alias) => (
+                syntax_tree.source(&name.child_by_field_name("name").unwrap_or(name)),
+                syntax_tree.source(&alias),
+              ),
+              None => (syntax_tree.source(&name), syntax_tree.source(&name)),
+            };
+            let name = match from.as_str() {
+              "" => String::from(name),
+              from => format!("{}.{}", from, name),
+            };
+            names.insert(String::from(alias), name);
+          }
+        }
+        _ => (),
+      }
+    });
+    Names { names }
+  }
+
+  // The qualified name of a (dotted) name, if its first part is bound at the top level
+  fn resolve(&self, name: &str) -> Option<String> {
+    let (first, rest) = match name.split_once('.') {
+      Some((first, rest)) => (first, Some(rest)),
+      None => (name, None),
+    };
+    let qualified_name = self.names.get(first)?;
+    Some(match rest {
+      Some(rest) => format!("{}.{}", qualified_name, rest),
+      None => qualified_name.clone(),
+    })
+  }
+}
+
+// Resolve a relative import against the package of the importing module, e.g., `..b` in `pkg.a`
+// refers to `b`
+fn relative_module(name: &str, package: &str) -> String {
+  let relative_name = name.trim_start_matches('.');
+  let levels = name.len() - relative_name.len();
+  if levels == 0 {
+    return String::from(name);
+  }
+  let mut package = package;
+  for _ in 1..levels {
+    package = package.rsplit_once('.').map_or("", |(parent, _)| parent);
+  }
+  match (package, relative_name) {
+    ("", name) | (name, "") => String::from(name),
+    (package, name) => format!("{}.{}", package, name),
+  }
+}
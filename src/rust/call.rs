@@ -0,0 +1,178 @@
+use crate::analyzer;
+use crate::graph;
+use crate::node;
+use crate::syntaxtree;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use super::Scope;
+
+type Call = node::Node;
+
+pub struct CallAnalyzer {
+  method_pool: OnceLock<HashMap<String, Call>>,
+  function_pool: OnceLock<HashMap<String, Option<Call>>>,
+}
+
+// The calls made by the functions of a file, in the order they are found
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Calls {
+  calls: Vec<(String, Callee)>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+enum Callee {
+  // e.g., `f()`, `Type::f()` or `module::f()`, by the paths it may stand for, along with how it is
+  // written
+  Path(Vec<String>, String),
+  // e.g., `x.f()`, along with the type of the impl if `x` is `self`
+  Method(Option<String>, String),
+}
+
+impl CallAnalyzer {
+  pub fn new() -> Self {
+    CallAnalyzer {
+      method_pool: OnceLock::new(),
+      function_pool: OnceLock::new(),
+    }
+  }
+}
+
+// Calls to names in upper camel case construct tuple structs or enum variants (e.g., `Some(x)`)
+fn callee(
+  syntax_tree: &syntaxtree::SyntaxTree,
+  function: &tree_sitter::Node,
+  scopes: &[(usize, Scope)],
+) -> Option<Callee> {
+  let is_function = |name: &str| !name.starts_with(|c: char| c.is_ascii_uppercase());
+  match function.kind() {
+    "identifier" | "scoped_identifier" => {
+      let name = match function.child_by_field_name("name") {
+        Some(name) => syntax_tree.source(&name),
+        None => syntax_tree.source(function),
+      };
+      if !is_function(name) {
+        return None;
+      }
+      let path = match function.child_by_field_name("path") {
+        Some(path) => format!("{}::{}", super::type_name(syntax_tree, &path), name),
+        None => String::from(name),
+      };
+      let candidates = super::resolve(&path, scopes)
+        .into_iter()
+        .map(|candidate| format!("{}()", candidate))
+        .collect();
+      Some(Callee::Path(candidates, format!("{}()", path)))
+    }
+    "field_expression" => {
+      let name = syntax_tree.source(&function.child_by_field_name("field")?);
+      let ty = function
+        .child_by_field_name("value")
+        .filter(|value| syntax_tree.source(value) == "self")
+        .and_then(|_| super::self_type(scopes));
+      Some(Callee::Method(ty.cloned(), String::from(name)))
+    }
+    "generic_function" => callee(
+      syntax_tree,
+      &function.child_by_field_name("function")?,
+      scopes,
+    ),
+    _ => None,
+  }
+}
+
+impl analyzer::Analyzer for CallAnalyzer {
+  type Summary = Calls;
+
+  fn name(&self) -> &'static str {
+    "call"
+  }
+
+  fn extract_nodes(&self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph) {
+    super::walk(syntax_tree, |node, scopes| {
+      if node.kind() != "function_item" {
+        return;
+      }
+      if let Some(name) = node.child_by_field_name("name") {
+        graph.add_node(&Call::new(
+          &super::function_name(syntax_tree.source(&name), scopes),
+          &syntax_tree.file,
+          name.start_position().row + 1,
+        ));
+      }
+    });
+  }
+
+  fn summarize(&self, syntax_tree: &syntaxtree::SyntaxTree) -> Calls {
+    let mut calls = vec![];
+    super::walk(syntax_tree, |node, scopes| {
+      if node.kind() != "call_expression" {
+        return;
+      }
+      let caller = scopes.iter().rev().find_map(|(_, scope)| match scope {
+        Scope::Function(function) => Some(function),
+        _ => None,
+      });
+      if let (Some(caller), Some(callee)) = (
+        caller,
+        node
+          .child_by_field_name("function")
+          .and_then(|function| callee(syntax_tree, &function, scopes)),
+      ) {
+        calls.push((caller.clone(), callee));
+      }
+    });
+    Calls { calls }
+  }
+
+  // Functions not found by their path are looked up by their unqualified name (e.g., `Conn::new()`
+  // for `net::Conn::new()`), since the modules of other files are unknown
+  fn extract_edges(&self, calls: &Calls, graph: &graph::Graph, partial_graph: &mut graph::Graph) {
+    let method_pool = self.method_pool.get_or_init(|| {
+      let mut method_pool = HashMap::<String, Call>::new();
+      for (qualified_name, call) in graph.nodes.iter() {
+        if let Some((_, method)) = analyzer::unqualified(qualified_name, "::").rsplit_once("::") {
+          method_pool
+            .entry(String::from(method))
+            .and_modify(|pooled| pooled.merge_node(call))
+            .or_insert_with(|| call.clone());
+        }
+      }
+      method_pool
+    });
+    let function_pool = self
+      .function_pool
+      .get_or_init(|| analyzer::unqualified_pool(graph, "::"));
+
+    for (caller, callee) in calls.calls.iter() {
+      let call = match graph.get_node(caller) {
+        Some(call) => call,
+        None => continue,
+      };
+      let (callee, name) = match callee {
+        Callee::Path(candidates, written) => (
+          analyzer::lookup(candidates, graph, function_pool, "::"),
+          written.clone(),
+        ),
+        Callee::Method(ty, name) => {
+          let method = format!("{}()", name);
+          let callee = ty
+            .as_ref()
+            .and_then(|ty| graph.get_node(&format!("{}::{}", ty, method)))
+            .or_else(|| method_pool.get(&method));
+          (callee, method)
+        }
+      };
+      match callee {
+        Some(callee) => partial_graph.add_edge(call, callee),
+        None => {
+          let callee = Call::new_without_loc(&name);
+          partial_graph.add_node(&callee);
+          partial_graph.add_edge(call, &callee);
+        }
+      }
+    }
+  }
+}
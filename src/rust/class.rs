@@ -0,0 +1,139 @@
+use crate::analyzer;
+use crate::graph;
+use crate::node;
+use crate::syntaxtree;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use super::Scope;
+
+type Class = node::Node;
+
+pub struct ClassAnalyzer {
+  class_pool: OnceLock<HashMap<String, Option<Class>>>,
+}
+
+// A type or a trait along with the traits it derives from, each by the paths it may stand for and
+// how it is written
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Inheritance {
+  class: Vec<String>,
+  baseclasses: Vec<(Vec<String>, String)>,
+}
+
+impl ClassAnalyzer {
+  pub fn new() -> Self {
+    ClassAnalyzer {
+      class_pool: OnceLock::new(),
+    }
+  }
+}
+
+// A trait is shown as a base class of its subtraits and of the types implementing them, e.g.,
+// `trait A: B` and `impl A for T` make B -> A -> T
+impl analyzer::Analyzer for ClassAnalyzer {
+  type Summary = Vec<Inheritance>;
+
+  fn name(&self) -> &'static str {
+    "class"
+  }
+
+  fn extract_nodes(&self, syntax_tree: &syntaxtree::SyntaxTree, graph: &mut graph::Graph) {
+    super::walk(syntax_tree, |node, scopes| {
+      let new_class = match node.kind() {
+        "struct_item" | "enum_item" | "union_item" | "trait_item" => Class::new,
+        // Associated types (i.e., in a trait or an impl) are not aliases of their own
+        "type_item" if !matches!(scopes.last(), Some((_, Scope::Impl(_) | Scope::Trait(_)))) => {
+          Class::new_alias
+        }
+        _ => return,
+      };
+      if let Some(name) = node.child_by_field_name("name") {
+        graph.add_node(&new_class(
+          &super::qualify(syntax_tree.source(&name), scopes),
+          &syntax_tree.file,
+          name.start_position().row + 1,
+        ));
+      }
+    });
+  }
+
+  fn summarize(&self, syntax_tree: &syntaxtree::SyntaxTree) -> Vec<Inheritance> {
+    let mut inheritances = Vec::<Inheritance>::new();
+    super::walk(syntax_tree, |node, scopes| {
+      let baseclass = |node: &tree_sitter::Node| {
+        let name = super::type_name(syntax_tree, node);
+        (super::resolve(&name, scopes), name)
+      };
+      let (class, baseclasses) = match node.kind() {
+        "trait_item" => match (
+          node.child_by_field_name("name"),
+          node.child_by_field_name("bounds"),
+        ) {
+          (Some(name), Some(bounds)) => {
+            let mut cursor = bounds.walk();
+            let baseclasses = bounds
+              .named_children(&mut cursor)
+              .filter(|bound| {
+                matches!(
+                  bound.kind(),
+                  "type_identifier" | "scoped_type_identifier" | "generic_type"
+                )
+              })
+              .map(|bound| baseclass(&bound))
+              .collect();
+            (
+              vec![super::qualify(syntax_tree.source(&name), scopes)],
+              baseclasses,
+            )
+          }
+          _ => return,
+        },
+        "impl_item" => match (
+          node.child_by_field_name("type"),
+          node.child_by_field_name("trait"),
+        ) {
+          (Some(ty), Some(trait_)) => (
+            super::resolve(&super::type_name(syntax_tree, &ty), scopes),
+            vec![baseclass(&trait_)],
+          ),
+          _ => return,
+        },
+        _ => return,
+      };
+      inheritances.push(Inheritance { class, baseclasses });
+    });
+    inheritances
+  }
+
+  // Types and traits not found by their path are looked up by their unqualified name (e.g., `Conn`
+  // for `net::Conn`), since the modules of other files are unknown
+  fn extract_edges(
+    &self,
+    inheritances: &Vec<Inheritance>,
+    graph: &graph::Graph,
+    partial_graph: &mut graph::Graph,
+  ) {
+    let class_pool = self
+      .class_pool
+      .get_or_init(|| analyzer::unqualified_pool(graph, "::"));
+    for inheritance in inheritances {
+      let class = match analyzer::lookup(&inheritance.class, graph, class_pool, "::") {
+        Some(class) => class,
+        None => continue,
+      };
+      for (candidates, written) in inheritance.baseclasses.iter() {
+        match analyzer::lookup(candidates, graph, class_pool, "::") {
+          Some(baseclass) => partial_graph.add_edge(baseclass, class),
+          None => {
+            let baseclass = Class::new_without_loc(written);
+            partial_graph.add_node(&baseclass);
+            partial_graph.add_edge(&baseclass, class);
+          }
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,242 @@
+pub mod call;
+pub mod class;
+
+use crate::analyzer;
+use crate::language::{self, Grammar};
+use crate::syntaxtree;
+
+use std::collections::HashMap;
+
+pub struct Rust;
+
+impl language::Language for Rust {
+  fn grammars(&self) -> &'static [(&'static str, Grammar)] {
+    &[("rust", Grammar::Rust), ("rs", Grammar::Rust)]
+  }
+
+  fn extensions(&self) -> &'static [(&'static str, Grammar)] {
+    &[("rs", Grammar::Rust)]
+  }
+
+  fn tree_sitter(&self, _grammar: Grammar) -> tree_sitter::Language {
+    tree_sitter_rust::language()
+  }
+
+  fn analyzer(&self, kind: analyzer::Kind) -> Option<Box<dyn analyzer::AnyAnalyzer>> {
+    match kind {
+      analyzer::Kind::Call => Some(Box::new(call::CallAnalyzer::new())),
+      analyzer::Kind::Class => Some(Box::new(class::ClassAnalyzer::new())),
+      analyzer::Kind::Include => None,
+    }
+  }
+}
+
+// The name of a type without its generic arguments, e.g., `Vec` for `Vec<T>`
+fn type_name(syntax_tree: &syntaxtree::SyntaxTree, node: &tree_sitter::Node) -> String {
+  let source = syntax_tree.source(node);
+  let name = match source.find('<') {
+    Some(index) => &source[..index],
+    None => source,
+  };
+  String::from(name.trim_end_matches("::").trim())
+}
+
+// The modules, impls, traits and functions enclosing a node by their path from the crate root,
+// each along with the byte where it ends. Modules are inline `mod` items, since those of other
+// files aren't known from the file itself.
+enum Scope {
+  // Along with the candidate paths of the names it imports with `use`
+  Module(String, HashMap<String, Vec<String>>),
+  Impl(String),
+  Trait(String),
+  Function(String),
+}
+
+// Walk the items of a file, calling `visit` with the scopes around each node, the outermost being
+// the crate root. Items are qualified by their module (e.g., `net::Conn`), and functions defined in
+// an impl or a trait by its type (e.g., `net::Conn::f()`).
+fn walk<'a>(
+  syntax_tree: &'a syntaxtree::SyntaxTree,
+  mut visit: impl FnMut(&tree_sitter::Node<'a>, &[(usize, Scope)]),
+) {
+  let mut scopes = vec![(usize::MAX, Scope::Module(String::new(), HashMap::new()))];
+  for node in syntax_tree.iter() {
+    while scopes
+      .last()
+      .is_some_and(|(end, _)| node.start_byte() >= *end)
+    {
+      scopes.pop();
+    }
+    visit(&node, &scopes);
+
+    let scope = match node.kind() {
+      "mod_item" if node.child_by_field_name("body").is_some() => node
+        .child_by_field_name("name")
+        .map(|name| Scope::Module(qualify(syntax_tree.source(&name), &scopes), HashMap::new())),
+      "impl_item" => node.child_by_field_name("type").and_then(|ty| {
+        let candidates = resolve(&type_name(syntax_tree, &ty), &scopes);
+        candidates.into_iter().next().map(Scope::Impl)
+      }),
+      "trait_item" => node
+        .child_by_field_name("name")
+        .map(|name| Scope::Trait(qualify(syntax_tree.source(&name), &scopes))),
+      "function_item" => node
+        .child_by_field_name("name")
+        .map(|name| Scope::Function(function_name(syntax_tree.source(&name), &scopes))),
+      "use_declaration" => {
+        if let Some(argument) = node.child_by_field_name("argument") {
+          let mut imports = HashMap::new();
+          import(syntax_tree, &argument, "", &mut imports);
+          let imports = imports
+            .into_iter()
+            .map(|(name, path)| (name, resolve(&path, &scopes)))
+            .collect::<Vec<_>>();
+          if let Some(Scope::Module(_, module_imports)) = scopes
+            .iter_mut()
+            .rev()
+            .map(|(_, scope)| scope)
+            .find(|scope| matches!(scope, Scope::Module(..)))
+          {
+            module_imports.extend(imports);
+          }
+        }
+        None
+      }
+      _ => None,
+    };
+    if let Some(scope) = scope {
+      scopes.push((node.end_byte(), scope));
+    }
+  }
+}
+
+// The paths imported by a `use` declaration by the name they are imported as, e.g., `R` to
+// `std::io::Read` for `use std::io::{self, Read as R}`. Glob imports are not followed.
+fn import(
+  syntax_tree: &syntaxtree::SyntaxTree,
+  node: &tree_sitter::Node,
+  prefix: &str,
+  imports: &mut HashMap<String, String>,
+) {
+  let join = |path: &str| match prefix {
+    "" => String::from(path),
+    prefix => format!("{}::{}", prefix, path),
+  };
+  let last = |path: &str| String::from(path.rsplit("::").next().unwrap_or(path));
+  match node.kind() {
+    "identifier" | "scoped_identifier" => {
+      let path = join(syntax_tree.source(node));
+      imports.insert(last(&path), path);
+    }
+    "self" if !prefix.is_empty() => {
+      imports.insert(last(prefix), String::from(prefix));
+    }
+    "use_as_clause" => {
+      if let (Some(path), Some(alias)) = (
+        node.child_by_field_name("path"),
+        node.child_by_field_name("alias"),
+      ) {
+        imports.insert(
+          String::from(syntax_tree.source(&alias)),
+          join(syntax_tree.source(&path)),
+        );
+      }
+    }
+    "scoped_use_list" => {
+      let prefix = match node.child_by_field_name("path") {
+        Some(path) => join(syntax_tree.source(&path)),
+        None => String::from(prefix),
+      };
+      if let Some(list) = node.child_by_field_name("list") {
+        import(syntax_tree, &list, &prefix, imports);
+      }
+    }
+    "use_list" => {
+      let mut cursor = node.walk();
+      for child in node.named_children(&mut cursor) {
+        import(syntax_tree, &child, prefix, imports);
+      }
+    }
+    _ => (),
+  }
+}
+
+fn module(scopes: &[(usize, Scope)]) -> &str {
+  scopes
+    .iter()
+    .rev()
+    .find_map(|(_, scope)| match scope {
+      Scope::Module(module, _) => Some(module.as_str()),
+      _ => None,
+    })
+    .unwrap_or_default()
+}
+
+// Qualify the name of an item by its module
+fn qualify(name: &str, scopes: &[(usize, Scope)]) -> String {
+  match module(scopes) {
+    "" => String::from(name),
+    module => format!("{}::{}", module, name),
+  }
+}
+
+fn function_name(name: &str, scopes: &[(usize, Scope)]) -> String {
+  match scopes.last() {
+    Some((_, Scope::Impl(ty) | Scope::Trait(ty))) => format!("{}::{}()", ty, name),
+    _ => format!("{}()", qualify(name, scopes)),
+  }
+}
+
+fn self_type(scopes: &[(usize, Scope)]) -> Option<&String> {
+  scopes.iter().rev().find_map(|(_, scope)| match scope {
+    Scope::Impl(ty) | Scope::Trait(ty) => Some(ty),
+    _ => None,
+  })
+}
+
+// The paths from the crate root that a path written in the innermost module may stand for, most
+// likely first: those through `crate`, `self`, `super`, `Self` or the imports of the module, or else
+// an item of the module itself, then the path as written
+fn resolve(path: &str, scopes: &[(usize, Scope)]) -> Vec<String> {
+  let join = |prefix: &str, rest: &[&str]| {
+    prefix
+      .split("::")
+      .filter(|segment| !segment.is_empty())
+      .chain(rest.iter().copied())
+      .collect::<Vec<_>>()
+      .join("::")
+  };
+  let segments = path.split("::").collect::<Vec<_>>();
+  let module = module(scopes);
+  match segments[0] {
+    "crate" => vec![join("", &segments[1..])],
+    "self" => vec![join(module, &segments[1..])],
+    "Self" => self_type(scopes)
+      .map(|ty| join(ty, &segments[1..]))
+      .into_iter()
+      .collect(),
+    "super" => {
+      let supers = segments
+        .iter()
+        .take_while(|segment| **segment == "super")
+        .count();
+      let mut parent = module.split("::").collect::<Vec<_>>();
+      parent.truncate(parent.len().saturating_sub(supers));
+      vec![join(&parent.join("::"), &segments[supers..])]
+    }
+    first => {
+      let imported = scopes.iter().rev().find_map(|(_, scope)| match scope {
+        Scope::Module(_, imports) => Some(imports.get(first)),
+        _ => None,
+      });
+      match imported.flatten() {
+        Some(candidates) => candidates
+          .iter()
+          .map(|candidate| join(candidate, &segments[1..]))
+          .collect(),
+        None if module.is_empty() => vec![String::from(path)],
+        None => vec![join(module, &segments), String::from(path)],
+      }
+    }
+  }
+}
@@ -1,14 +1,31 @@
+use crate::compdb;
+use crate::language;
+use crate::preprocessor;
+
 use anyhow::{Context, Result};
-use std::fs;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::iter;
 use std::path;
+use std::sync::OnceLock;
 
 pub struct SyntaxTree {
   pub file: String,
+  pub grammar: language::Grammar,
+  // Where the headers included by the file are searched
+  pub include_paths: Vec<path::PathBuf>,
   source: String,
   tree: tree_sitter::Tree,
 }
 
+// Where tree-sitter failed to parse a construct (an ERROR or MISSING node)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ParseError {
+  pub row: usize,
+  // A macro on the same line that likely confused tree-sitter, as an --ignore-macro argument
+  pub candidate: Option<String>,
+}
+
 pub struct NodeIterator<'a> {
   cursor: tree_sitter::TreeCursor<'a>,
   dir: Direction,
@@ -22,21 +39,29 @@ enum Direction {
 }
 
 impl SyntaxTree {
-  pub fn new(file: path::PathBuf, ignore_macros: &[String]) -> Result<Self> {
+  pub fn new(
+    file: path::PathBuf,
+    grammar: language::Grammar,
+    source: String,
+    flags: &compdb::CompileFlags,
+    preprocessor: &preprocessor::Preprocessor,
+  ) -> Result<Self> {
     let mut parser = tree_sitter::Parser::new();
     parser
-      .set_language(tree_sitter_cpp::language())
-      .context("Failed to load Cpp grammar!")?;
+      .set_language(grammar.tree_sitter())
+      .with_context(|| format!("Failed to load {:?} grammar!", grammar))?;
 
-    let mut source =
-      fs::read_to_string(&file).with_context(|| format!("Failed to read: {}!", file.display()))?;
-    ignore_macros.iter().for_each(|ignore_macro| {
-      source = source.replace(ignore_macro, &"".repeat(ignore_macro.len())); // Replace with blank placeholder
-    });
+    let source = if grammar.preprocessed() {
+      preprocessor.run(source, flags)
+    } else {
+      source
+    };
 
     if let Some(tree) = parser.parse(&source, None) {
       Ok(SyntaxTree {
         file: file.display().to_string(),
+        grammar,
+        include_paths: flags.include_paths.clone(),
         source,
         tree,
       })
@@ -45,13 +70,95 @@ impl SyntaxTree {
     }
   }
 
-  pub fn iter(&self) -> NodeIterator {
+  pub fn iter(&self) -> NodeIterator<'_> {
     NodeIterator::new(&self.tree)
   }
 
   pub fn source(&self, node: &tree_sitter::Node) -> &str {
     &self.source[node.start_byte()..node.end_byte()]
   }
+
+  // Errors nested in another ERROR node are part of it, so they are not reported again
+  pub fn errors(&self) -> Vec<ParseError> {
+    if !self.tree.root_node().has_error() {
+      return vec![];
+    }
+    self
+      .iter()
+      .filter(|node| node.is_error() || node.is_missing())
+      .filter(|node| {
+        iter::successors(node.parent(), |parent| parent.parent()).all(|parent| !parent.is_error())
+      })
+      .map(|node| {
+        let position = node.start_position();
+        ParseError {
+          row: position.row + 1,
+          candidate: self
+            .grammar
+            .preprocessed()
+            .then(|| {
+              macro_candidate(
+                self.source.lines().nth(position.row).unwrap_or_default(),
+                position.column,
+              )
+            })
+            .flatten(),
+        }
+      })
+      .collect()
+  }
+}
+
+// Macros are conventionally upper case (e.g., `LEVELDB_EXPORT` or `GUARDED_BY(mu)`), or
+// function-like and followed by the rest of a declaration (e.g., `PyAPI_FUNC(void) f();`). The one
+// closest before the error is taken, or else the first one after it.
+fn macro_candidate(line: &str, column: usize) -> Option<String> {
+  static ANNOTATION: OnceLock<Regex> = OnceLock::new();
+  static MACRO: OnceLock<Regex> = OnceLock::new();
+  let annotation = ANNOTATION.get_or_init(|| {
+    Regex::new(r"^\s*([A-Za-z_]\w*[A-Z_]\w*|[A-Z_]\w*)\s*\([^()]*\)\s*[A-Za-z_]").unwrap()
+  });
+  let regex =
+    MACRO.get_or_init(|| Regex::new(r"\b([A-Z_][A-Z0-9_]*[A-Z][A-Z0-9_]*)\b(\s*\()?").unwrap());
+  let mut candidates = regex
+    .captures_iter(line)
+    .filter(|captures| !matches!(&captures[1], "NULL" | "EOF" | "TRUE" | "FALSE"))
+    .map(|captures| {
+      let candidate = match captures.get(2) {
+        Some(_) => format!("{}(...)", &captures[1]),
+        None => String::from(&captures[1]),
+      };
+      (captures.get(1).map_or(0, |name| name.start()), candidate)
+    })
+    .collect::<Vec<_>>();
+  if let Some(captures) = annotation.captures(line) {
+    candidates.push((
+      captures.get(1).map_or(0, |name| name.start()),
+      format!("{}(...)", &captures[1]),
+    ));
+  }
+  candidates
+    .iter()
+    .filter(|(start, _)| *start <= column)
+    .max_by_key(|(start, _)| *start)
+    .or_else(|| candidates.iter().find(|(start, _)| *start > column))
+    .map(|(_, candidate)| candidate.clone())
+}
+
+// Sources that aren't UTF-8 (e.g., with Latin-1 or GBK comments) are decoded with `encoding`, or
+// a guessed one. Either way line breaks are kept, so rows match the original file, but byte
+// offsets are those of the decoded source, since a character may take more bytes in UTF-8.
+pub fn decode(bytes: Vec<u8>, encoding: Option<&'static encoding_rs::Encoding>) -> String {
+  let bytes = match String::from_utf8(bytes) {
+    Ok(source) => return source,
+    Err(e) => e.into_bytes(),
+  };
+  let encoding = encoding.unwrap_or_else(|| {
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(&bytes, true);
+    detector.guess(None, true)
+  });
+  encoding.decode(&bytes).0.into_owned()
 }
 
 impl<'a> NodeIterator<'a> {
@@ -85,3 +192,26 @@ impl<'a> iter::Iterator for NodeIterator<'a> {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn suggests_the_macro_at_the_error() {
+    let line = "void MY_API_HELPER() { GUARDED_BY(mu_) int x; }";
+    assert_eq!(
+      macro_candidate(line, 38).as_deref(),
+      Some("GUARDED_BY(...)")
+    );
+    assert_eq!(
+      macro_candidate("int y GUARDED_BY(mu);", 4).as_deref(),
+      Some("GUARDED_BY(...)")
+    );
+    assert_eq!(
+      macro_candidate("PyAPI_FUNC(void) f();", 0).as_deref(),
+      Some("PyAPI_FUNC(...)")
+    );
+    assert_eq!(macro_candidate("return NULL;", 0), None);
+  }
+}